@@ -0,0 +1,199 @@
+use std::{fmt, path::{Path, PathBuf}};
+use image::{RgbaImage, Rgba};
+
+/*------------------------------------------------------------------------------
+                    GOLDEN IMAGE COMPARISON
+------------------------------------------------------------------------------*/
+// Compares a rendered canvas against a reference image (usually one of the
+// renders kept in `out/`). Pixel error is the largest absolute channel
+// difference, so `tolerance` is expressed in 8-bit channel steps.
+
+const SSIM_WINDOW: u32 = 8;
+const SSIM_C1    : f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2    : f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+#[derive(Debug)]
+pub enum CompareError {
+    Dimensions{actual: (u32, u32), reference: (u32, u32)},
+    Image(image::ImageError),
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompareError::Dimensions{actual, reference} =>
+                write!(f, "image is {}x{} but reference is {}x{}",
+                       actual.0, actual.1, reference.0, reference.1),
+            CompareError::Image(e) => write!(f, "could not read or write image: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CompareError {}
+
+impl From<image::ImageError> for CompareError {
+    fn from(e: image::ImageError) -> CompareError {
+        CompareError::Image(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompareReport {
+    pub tolerance : u8,
+    pub mismatched: usize,   // pixels whose error exceeds `tolerance`
+    pub max_error : u8,
+    pub mean_error: f64,     // mean absolute channel difference
+    pub psnr      : f64,     // dB, infinite for identical images
+    pub ssim      : f64,     // mean SSIM over luma windows, 1.0 for identical images
+    pub diff      : RgbaImage,
+}
+
+impl CompareReport {
+    pub fn passed(&self) -> bool {
+        self.mismatched == 0
+    }
+}
+
+impl fmt::Display for CompareReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} of {} pixels over tolerance {} (max error {}, mean error {:.4}, PSNR {:.2} dB, SSIM {:.4})",
+               self.mismatched, self.diff.width() * self.diff.height(), self.tolerance,
+               self.max_error, self.mean_error, self.psnr, self.ssim)
+    }
+}
+
+pub fn compare(actual   : &RgbaImage,
+               reference: &RgbaImage,
+               tolerance: u8)
+    -> Result<CompareReport, CompareError> {
+    if actual.dimensions() != reference.dimensions() {
+        return Err(CompareError::Dimensions{actual: actual.dimensions(),
+                                            reference: reference.dimensions()});
+    }
+
+    let mut mismatched  = 0;
+    let mut max_error   = 0;
+    let mut sum_error   = 0u64;
+    let mut sum_squared = 0u64;
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+
+    for ((a, r), d) in actual.pixels().zip(reference.pixels()).zip(diff.pixels_mut()) {
+        let mut error = 0;
+        for c in 0..4 {
+            let e = a[c].abs_diff(r[c]);
+            sum_error   += e as u64;
+            sum_squared += e as u64 * e as u64;
+            error = error.max(e);
+        }
+        max_error = max_error.max(error);
+        if error > tolerance {mismatched += 1;}
+        *d = heat(error, tolerance, r);
+    }
+
+    let samples = (actual.width() * actual.height() * 4).max(1) as f64;
+    let mse     = sum_squared as f64 / samples;
+    let psnr    = if mse == 0.0 {f64::INFINITY} else {10.0 * (255.0 * 255.0 / mse).log10()};
+
+    Ok(CompareReport{tolerance,
+                     mismatched,
+                     max_error,
+                     mean_error: sum_error as f64 / samples,
+                     psnr,
+                     ssim: ssim(actual, reference),
+                     diff})
+}
+
+pub fn compare_to_file<P: AsRef<Path>>(actual   : &RgbaImage,
+                                       reference: P,
+                                       tolerance: u8)
+    -> Result<CompareReport, CompareError> {
+    let reference = image::open(reference)?.to_rgba8();
+    compare(actual, &reference, tolerance)
+}
+
+// Test helper: on failure writes `<name>.actual.png` and `<name>.diff.png` next
+// to the reference and panics with the report.
+pub fn assert_matches_reference<P: AsRef<Path>>(actual   : &RgbaImage,
+                                                reference: P,
+                                                tolerance: u8) {
+    let reference = reference.as_ref();
+    let report = match compare_to_file(actual, reference, tolerance) {
+        Ok(report) => report,
+        Err(e) => {
+            let _ = actual.save(sibling(reference, "actual"));
+            panic!("{}: {}", reference.display(), e);
+        }
+    };
+    if !report.passed() {
+        let _ = actual.save(sibling(reference, "actual"));
+        let _ = report.diff.save(sibling(reference, "diff"));
+        panic!("{} does not match: {}", reference.display(), report);
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}.png", stem, suffix))
+}
+
+// Pixels within tolerance show a dimmed copy of the reference, failures run
+// from red (just over tolerance) through yellow to white (maximum error).
+fn heat(error: u8, tolerance: u8, reference: &Rgba<u8>) -> Rgba<u8> {
+    if error <= tolerance {
+        let l = (luma(reference) / 4.0) as u8;
+        return Rgba([l, l, l, 255]);
+    }
+    let t = (error - tolerance) as f64 / (255 - tolerance) as f64;
+    let r = 128.0 + 127.0 * (t * 3.0).min(1.0);
+    let g = 255.0 * (t * 3.0 - 1.0).clamp(0.0, 1.0);
+    let b = 255.0 * (t * 3.0 - 2.0).clamp(0.0, 1.0);
+    Rgba([r as u8, g as u8, b as u8, 255])
+}
+
+fn luma(p: &Rgba<u8>) -> f64 {
+    0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64
+}
+
+// Mean SSIM over non-overlapping luma windows
+fn ssim(a: &RgbaImage, b: &RgbaImage) -> f64 {
+    let (width, height) = a.dimensions();
+    if width == 0 || height == 0 {return 1.0;}
+    let window = SSIM_WINDOW.min(width).min(height);
+    let mut total = 0.0;
+    let mut count = 0;
+
+    for wy in (0..=height - window).step_by(window as usize) {
+        for wx in (0..=width - window).step_by(window as usize) {
+            let n = (window * window) as f64;
+            let (mut ma, mut mb) = (0.0, 0.0);
+            for y in wy..wy + window {
+                for x in wx..wx + window {
+                    ma += luma(a.get_pixel(x, y));
+                    mb += luma(b.get_pixel(x, y));
+                }
+            }
+            ma /= n;
+            mb /= n;
+
+            let (mut va, mut vb, mut cov) = (0.0, 0.0, 0.0);
+            for y in wy..wy + window {
+                for x in wx..wx + window {
+                    let da = luma(a.get_pixel(x, y)) - ma;
+                    let db = luma(b.get_pixel(x, y)) - mb;
+                    va  += da * da;
+                    vb  += db * db;
+                    cov += da * db;
+                }
+            }
+            let dof = (n - 1.0).max(1.0);
+            va  /= dof;
+            vb  /= dof;
+            cov /= dof;
+
+            total += ((2.0 * ma * mb + SSIM_C1) * (2.0 * cov + SSIM_C2))
+                   / ((ma * ma + mb * mb + SSIM_C1) * (va + vb + SSIM_C2));
+            count += 1;
+        }
+    }
+    total / count as f64
+}
@@ -164,6 +164,31 @@ impl<T: Clone> Vec3<T> {
     }
 }
 
+impl Vec3<f32> {
+    pub fn dot(&self, other: &Vec3<f32>) -> f32 {
+        (self.x * other.x +
+         self.y * other.y +
+         self.z * other.z).value
+    }
+
+    pub fn cross(&self, other: &Vec3<f32>) -> Vec3<f32> {
+        Self{x: self.y * other.z - self.z * other.y,
+             y: self.z * other.x - self.x * other.z,
+             z: self.x * other.y - self.y * other.x}
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Vec3<f32> {
+        let length: Scalar::<f32> = Scalar::new(Vec3::norm(self));
+        Self{x: self.x / length,
+             y: self.y / length,
+             z: self.z / length}
+    }
+}
+
 
 
 /*------------------------------------------------------------------------------
@@ -1,8 +1,8 @@
-#[allow(unused_variables)]
-#[allow(unused_imports)]
-#[allow(dead_code)]
+#![allow(unused_variables)]
+#![allow(unused_imports)]
+#![allow(dead_code)]
 
-use image::RgbaImage;
+use image::{RgbaImage, ImageBuffer, Rgb, Luma};
 
 /*------------------------------------------------------------------------------
                     IMAGE OPERATIONS
@@ -14,7 +14,37 @@ pub fn set(image: &mut RgbaImage,
            color: [u8; 4])
     -> bool {
     image[(x as u32, y as u32)] = image::Rgba([color[0], color[1], color[2], color[3]]);
-    return true
+    true
+}
+
+// Source-over blend of `color` weighted by `coverage` (0..=1) onto the pixel
+pub fn blend(image   : &mut RgbaImage,
+             x       : i32,
+             y       : i32,
+             color   : [u8; 4],
+             coverage: f32)
+    -> bool {
+    let dst = image[(x as u32, y as u32)].0;
+    let a   = coverage.clamp(0.0, 1.0) * color[3] as f32 / 255.0;
+    let mut out = [0u8; 4];
+    for c in 0..3 {
+        out[c] = (color[c] as f32 * a + dst[c] as f32 * (1.0 - a)).round() as u8;
+    }
+    out[3] = (255.0 * a + dst[3] as f32 * (1.0 - a)).round() as u8;
+    image[(x as u32, y as u32)] = image::Rgba(out);
+    true
+}
+
+// Full coverage overwrites like `set`, partial coverage blends
+pub fn set_coverage(image   : &mut RgbaImage,
+                    x       : i32,
+                    y       : i32,
+                    color   : [u8; 4],
+                    coverage: f32)
+    -> bool {
+    if coverage >= 1.0 {set(image, x, y, color)}
+    else if coverage > 0.0 {blend(image, x, y, color, coverage)}
+    else {false}
 }
 
 pub fn set_all (image: &mut RgbaImage,
@@ -23,5 +53,22 @@ pub fn set_all (image: &mut RgbaImage,
     for (_x, _y, pixel) in image.enumerate_pixels_mut() {
         *pixel = image::Rgba([color[0], color[1], color[2], color[3]]);
     }
-    return true
+    true
 }
+
+// sRGB transfer functions on 0..=1 channel values
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {c / 12.92} else {((c + 0.055) / 1.055).powf(2.4)}
+}
+
+pub fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {c * 12.92} else {1.055 * c.powf(1.0 / 2.4) - 0.055}
+}
+
+/*------------------------------------------------------------------------------
+                    FLOAT BUFFERS
+------------------------------------------------------------------------------*/
+// Linear HDR color and per-pixel depth, stored like `RgbaImage` so the same
+// `ImageBuffer` helpers apply.
+pub type HdrImage    = ImageBuffer<Rgb<f32>, Vec<f32>>;
+pub type DepthBuffer = ImageBuffer<Luma<f32>, Vec<f32>>;
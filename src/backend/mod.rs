@@ -1,2 +1,5 @@
 pub mod images;
-pub mod geometry;
\ No newline at end of file
+pub mod geometry;
+pub mod compare;
+pub mod netpbm;
+pub mod palette;
\ No newline at end of file
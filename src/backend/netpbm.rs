@@ -0,0 +1,413 @@
+use std::{fmt, fs::File, io::{self, BufReader, BufWriter, Read, Write}, path::Path};
+use image::{RgbaImage, Rgba, Rgb, Luma};
+use crate::backend::images::{HdrImage, DepthBuffer};
+
+/*------------------------------------------------------------------------------
+                    NETPBM (PBM / PGM / PPM / PAM) AND PFM
+------------------------------------------------------------------------------*/
+// Dependency-free writers and readers for the netpbm family. Rows are written
+// in buffer order (top row first) except for PFM, whose rows run bottom to
+// top as the format requires.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NetpbmFormat {
+    PBM,    // 1-bit, black where luma < 50%
+    PGM,    // 8-bit luma
+    PPM,    // 8-bit RGB
+    PAM,    // 8-bit RGBA (binary only)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NetpbmEncoding {
+    ASCII,
+    BINARY,
+}
+
+#[derive(Debug)]
+pub enum NetpbmError {
+    Io(io::Error),
+    Format(String),
+}
+
+impl fmt::Display for NetpbmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetpbmError::Io(e)     => write!(f, "netpbm i/o error: {}", e),
+            NetpbmError::Format(m) => write!(f, "malformed netpbm data: {}", m),
+        }
+    }
+}
+
+impl std::error::Error for NetpbmError {}
+
+impl From<io::Error> for NetpbmError {
+    fn from(e: io::Error) -> NetpbmError {
+        NetpbmError::Io(e)
+    }
+}
+
+fn format_error<T>(message: &str) -> Result<T, NetpbmError> {
+    Err(NetpbmError::Format(message.to_string()))
+}
+
+fn luma(p: &Rgba<u8>) -> u8 {
+    ((299 * p[0] as u32 + 587 * p[1] as u32 + 114 * p[2] as u32 + 500) / 1000) as u8
+}
+
+/*------------------------------------------------------------------------------
+                    WRITERS
+------------------------------------------------------------------------------*/
+// Plain rasters start every image row on a new line and wrap before 70
+// characters, the longest line the format allows
+const PLAIN_LINE: usize = 70;
+
+fn write_plain_row<W: Write>(w: &mut W, samples: impl Iterator<Item = u8>) -> io::Result<()> {
+    let mut column = 0;
+    for sample in samples {
+        let text = sample.to_string();
+        if column > 0 && column + 1 + text.len() > PLAIN_LINE {
+            writeln!(w)?;
+            column = 0;
+        }
+        if column > 0 {
+            write!(w, " ")?;
+            column += 1;
+        }
+        write!(w, "{}", text)?;
+        column += text.len();
+    }
+    writeln!(w)
+}
+
+pub fn write_pnm<W: Write>(w       : &mut W,
+                           image   : &RgbaImage,
+                           format  : NetpbmFormat,
+                           encoding: NetpbmEncoding)
+    -> Result<(), NetpbmError> {
+    let (width, height) = image.dimensions();
+    match (format, encoding) {
+        (NetpbmFormat::PAM, NetpbmEncoding::ASCII) =>
+            return format_error("PAM has no ASCII encoding"),
+        (NetpbmFormat::PAM, NetpbmEncoding::BINARY) => {
+            write!(w, "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+                   width, height)?;
+            w.write_all(image.as_raw())?;
+        }
+        (NetpbmFormat::PBM, NetpbmEncoding::ASCII) => {
+            write!(w, "P1\n{} {}\n", width, height)?;
+            for row in image.rows() {
+                write_plain_row(w, row.map(|p| if luma(p) < 128 {1} else {0}))?;
+            }
+        }
+        (NetpbmFormat::PBM, NetpbmEncoding::BINARY) => {
+            write!(w, "P4\n{} {}\n", width, height)?;
+            let mut packed = vec![0u8; width.div_ceil(8) as usize];
+            for row in image.rows() {
+                packed.iter_mut().for_each(|b| *b = 0);
+                for (x, p) in row.enumerate() {
+                    if luma(p) < 128 {packed[x / 8] |= 0x80 >> (x % 8);}
+                }
+                w.write_all(&packed)?;
+            }
+        }
+        (NetpbmFormat::PGM, NetpbmEncoding::ASCII) => {
+            write!(w, "P2\n{} {}\n255\n", width, height)?;
+            for row in image.rows() {
+                write_plain_row(w, row.map(luma))?;
+            }
+        }
+        (NetpbmFormat::PGM, NetpbmEncoding::BINARY) => {
+            write!(w, "P5\n{} {}\n255\n", width, height)?;
+            let values: Vec<u8> = image.pixels().map(luma).collect();
+            w.write_all(&values)?;
+        }
+        (NetpbmFormat::PPM, NetpbmEncoding::ASCII) => {
+            write!(w, "P3\n{} {}\n255\n", width, height)?;
+            for row in image.rows() {
+                write_plain_row(w, row.flat_map(|p| [p[0], p[1], p[2]]))?;
+            }
+        }
+        (NetpbmFormat::PPM, NetpbmEncoding::BINARY) => {
+            write!(w, "P6\n{} {}\n255\n", width, height)?;
+            let values: Vec<u8> = image.pixels().flat_map(|p| [p[0], p[1], p[2]]).collect();
+            w.write_all(&values)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn save_pnm<P: AsRef<Path>>(path    : P,
+                                image   : &RgbaImage,
+                                format  : NetpbmFormat,
+                                encoding: NetpbmEncoding)
+    -> Result<(), NetpbmError> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_pnm(&mut w, image, format, encoding)?;
+    w.flush()?;
+    Ok(())
+}
+
+fn write_pfm_rows<W: Write>(w     : &mut W,
+                            magic : &str,
+                            width : u32,
+                            height: u32,
+                            data  : &[f32])
+    -> Result<(), NetpbmError> {
+    // Negative scale marks little-endian samples
+    write!(w, "{}\n{} {}\n-1.0\n", magic, width, height)?;
+    let stride = data.len() / height.max(1) as usize;
+    for row in data.chunks(stride.max(1)).rev() {
+        let bytes: Vec<u8> = row.iter().flat_map(|v| v.to_le_bytes()).collect();
+        w.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+pub fn write_pfm<W: Write>(w: &mut W, image: &HdrImage) -> Result<(), NetpbmError> {
+    write_pfm_rows(w, "PF", image.width(), image.height(), image.as_raw())
+}
+
+pub fn write_pfm_depth<W: Write>(w: &mut W, depth: &DepthBuffer) -> Result<(), NetpbmError> {
+    write_pfm_rows(w, "Pf", depth.width(), depth.height(), depth.as_raw())
+}
+
+pub fn save_pfm<P: AsRef<Path>>(path: P, image: &HdrImage) -> Result<(), NetpbmError> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_pfm(&mut w, image)?;
+    w.flush()?;
+    Ok(())
+}
+
+pub fn save_pfm_depth<P: AsRef<Path>>(path: P, depth: &DepthBuffer) -> Result<(), NetpbmError> {
+    let mut w = BufWriter::new(File::create(path)?);
+    write_pfm_depth(&mut w, depth)?;
+    w.flush()?;
+    Ok(())
+}
+
+/*------------------------------------------------------------------------------
+                    READERS
+------------------------------------------------------------------------------*/
+struct Parser {
+    data: Vec<u8>,
+    pos : usize,
+}
+
+impl Parser {
+    fn new<R: Read>(r: &mut R) -> Result<Parser, NetpbmError> {
+        let mut data = Vec::new();
+        r.read_to_end(&mut data)?;
+        Ok(Parser{data, pos: 0})
+    }
+
+    // Skips whitespace and `#` comments
+    fn skip_space(&mut self) {
+        while self.pos < self.data.len() {
+            match self.data[self.pos] {
+                b'#' => while self.pos < self.data.len() && self.data[self.pos] != b'\n' {self.pos += 1;},
+                c if c.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<String, NetpbmError> {
+        self.skip_space();
+        let start = self.pos;
+        while self.pos < self.data.len() && !self.data[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {return format_error("unexpected end of header");}
+        Ok(String::from_utf8_lossy(&self.data[start..self.pos]).into_owned())
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> Result<T, NetpbmError> {
+        let token = self.token()?;
+        token.parse().or_else(|_| format_error(&format!("expected a number, found `{}`", token)))
+    }
+
+    // PBM ASCII rasters may pack bits without separating whitespace
+    fn bit(&mut self) -> Result<bool, NetpbmError> {
+        self.skip_space();
+        match self.data.get(self.pos) {
+            Some(b'0') => {self.pos += 1; Ok(false)}
+            Some(b'1') => {self.pos += 1; Ok(true)}
+            _ => format_error("expected a PBM bit"),
+        }
+    }
+
+    // The single whitespace byte between a binary header and its raster
+    fn raster(&mut self, len: usize) -> Result<&[u8], NetpbmError> {
+        self.pos += 1;
+        if len > self.data.len().saturating_sub(self.pos) {return format_error("raster is truncated");}
+        let start = self.pos;
+        self.pos += len;
+        Ok(&self.data[start..self.pos])
+    }
+}
+
+fn scale(value: u32, maxval: u32) -> u8 {
+    if maxval == 255 {value.min(255) as u8} else {((value.min(maxval) * 255 + maxval / 2) / maxval) as u8}
+}
+
+fn read_samples(parser: &mut Parser,
+                binary: bool,
+                count : usize,
+                maxval: u32)
+    -> Result<Vec<u8>, NetpbmError> {
+    if maxval == 0 || maxval > 65535 {return format_error("maxval must be in 1..=65535");}
+    if !binary {
+        return (0..count).map(|_| parser.number::<u32>().map(|v| scale(v, maxval))).collect();
+    }
+    if maxval < 256 {
+        Ok(parser.raster(count)?.iter().map(|&v| scale(v as u32, maxval)).collect())
+    } else {
+        Ok(parser.raster(count * 2)?
+                 .chunks(2)
+                 .map(|b| scale(u16::from_be_bytes([b[0], b[1]]) as u32, maxval))
+                 .collect())
+    }
+}
+
+// Depth 2 is gray + alpha, depth 4 is RGBA
+fn from_channels(width  : u32,
+                 height : u32,
+                 depth  : usize,
+                 samples: &[u8])
+    -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| {
+        let s = &samples[(y * width + x) as usize * depth..][..depth];
+        match depth {
+            1 => Rgba([s[0], s[0], s[0], 255]),
+            2 => Rgba([s[0], s[0], s[0], s[1]]),
+            3 => Rgba([s[0], s[1], s[2], 255]),
+            _ => Rgba([s[0], s[1], s[2], s[3]]),
+        }
+    })
+}
+
+pub fn read_pnm<R: Read>(r: &mut R) -> Result<RgbaImage, NetpbmError> {
+    let mut parser = Parser::new(r)?;
+    let magic = parser.token()?;
+
+    if magic == "P7" {
+        return read_pam(&mut parser);
+    }
+
+    let width : u32 = parser.number()?;
+    let height: u32 = parser.number()?;
+    let pixels = width as usize * height as usize;
+
+    match magic.as_str() {
+        "P1" => {
+            let bits = (0..pixels).map(|_| parser.bit()).collect::<Result<Vec<_>, _>>()?;
+            let samples: Vec<u8> = bits.iter().map(|&b| if b {0} else {255}).collect();
+            Ok(from_channels(width, height, 1, &samples))
+        }
+        "P4" => {
+            let stride = width.div_ceil(8) as usize;
+            let packed = parser.raster(stride * height as usize)?;
+            Ok(RgbaImage::from_fn(width, height, |x, y| {
+                let byte = packed[y as usize * stride + x as usize / 8];
+                let v = if byte & (0x80 >> (x % 8)) != 0 {0} else {255};
+                Rgba([v, v, v, 255])
+            }))
+        }
+        "P2" | "P5" | "P3" | "P6" => {
+            let maxval: u32 = parser.number()?;
+            let depth = if magic == "P2" || magic == "P5" {1} else {3};
+            let binary = magic == "P5" || magic == "P6";
+            let samples = read_samples(&mut parser, binary, pixels * depth, maxval)?;
+            Ok(from_channels(width, height, depth, &samples))
+        }
+        _ => format_error(&format!("unknown magic number `{}`", magic)),
+    }
+}
+
+fn read_pam(parser: &mut Parser) -> Result<RgbaImage, NetpbmError> {
+    let (mut width, mut height, mut depth, mut maxval) = (None, None, None, None);
+    let mut tupltype = String::new();
+    loop {
+        let key = parser.token()?;
+        match key.as_str() {
+            "WIDTH"    => width  = Some(parser.number::<u32>()?),
+            "HEIGHT"   => height = Some(parser.number::<u32>()?),
+            "DEPTH"    => depth  = Some(parser.number::<usize>()?),
+            "MAXVAL"   => maxval = Some(parser.number::<u32>()?),
+            "TUPLTYPE" => tupltype = parser.token()?,
+            "ENDHDR"   => break,
+            _ => return format_error(&format!("unknown PAM header field `{}`", key)),
+        }
+    }
+    let (width, height, depth, maxval) = match (width, height, depth, maxval) {
+        (Some(w), Some(h), Some(d), Some(m)) => (w, h, d, m),
+        _ => return format_error("PAM header is missing WIDTH, HEIGHT, DEPTH or MAXVAL"),
+    };
+    if depth == 0 || depth > 4 {return format_error("PAM depth must be 1 to 4");}
+
+    let mut samples = read_samples(parser, true, width as usize * height as usize * depth, maxval)?;
+    if tupltype.starts_with("BLACKANDWHITE") {
+        // 1 means white in PAM, unlike PBM
+        for (i, s) in samples.iter_mut().enumerate() {
+            if i % depth == 0 {*s = if *s > 0 {255} else {0};}
+        }
+    }
+    Ok(from_channels(width, height, depth, &samples))
+}
+
+pub fn load_pnm<P: AsRef<Path>>(path: P) -> Result<RgbaImage, NetpbmError> {
+    read_pnm(&mut BufReader::new(File::open(path)?))
+}
+
+// Returns the channel count (3 for `PF`, 1 for `Pf`) and the samples in buffer
+// order (top row first).
+fn read_pfm_samples<R: Read>(r: &mut R) -> Result<(u32, u32, usize, Vec<f32>), NetpbmError> {
+    let mut parser = Parser::new(r)?;
+    let channels = match parser.token()?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        other => return format_error(&format!("unknown PFM magic number `{}`", other)),
+    };
+    let width : u32 = parser.number()?;
+    let height: u32 = parser.number()?;
+    let scale : f32 = parser.number()?;
+    if width == 0 || height == 0 {return format_error("PFM dimensions must be non-zero");}
+
+    let stride = width as usize * channels;
+    let len = match stride.checked_mul(height as usize).and_then(|n| n.checked_mul(4)) {
+        Some(len) => len,
+        None => return format_error("PFM dimensions are too large"),
+    };
+    let raster = parser.raster(len)?;
+    let mut samples = Vec::with_capacity(stride * height as usize);
+    for row in raster.chunks(stride * 4).rev() {
+        samples.extend(row.chunks(4).map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            if scale < 0.0 {f32::from_le_bytes(bytes)} else {f32::from_be_bytes(bytes)}
+        }));
+    }
+    Ok((width, height, channels, samples))
+}
+
+pub fn read_pfm<R: Read>(r: &mut R) -> Result<HdrImage, NetpbmError> {
+    let (width, height, channels, samples) = read_pfm_samples(r)?;
+    Ok(HdrImage::from_fn(width, height, |x, y| {
+        let i = (y * width + x) as usize * channels;
+        if channels == 3 {Rgb([samples[i], samples[i + 1], samples[i + 2]])}
+        else {Rgb([samples[i]; 3])}
+    }))
+}
+
+pub fn read_pfm_depth<R: Read>(r: &mut R) -> Result<DepthBuffer, NetpbmError> {
+    let (width, height, channels, samples) = read_pfm_samples(r)?;
+    if channels != 1 {return format_error("depth buffers need a grayscale (Pf) file");}
+    Ok(DepthBuffer::from_fn(width, height, |x, y| Luma([samples[(y * width + x) as usize]])))
+}
+
+pub fn load_pfm<P: AsRef<Path>>(path: P) -> Result<HdrImage, NetpbmError> {
+    read_pfm(&mut BufReader::new(File::open(path)?))
+}
+
+pub fn load_pfm_depth<P: AsRef<Path>>(path: P) -> Result<DepthBuffer, NetpbmError> {
+    read_pfm_depth(&mut BufReader::new(File::open(path)?))
+}
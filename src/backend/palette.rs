@@ -0,0 +1,359 @@
+use std::{collections::HashMap, sync::OnceLock};
+use image::{RgbaImage, Rgba};
+
+/*------------------------------------------------------------------------------
+                    PALETTES
+------------------------------------------------------------------------------*/
+// Palettes hold at most 256 RGBA entries so an indexed image fits in one byte
+// per pixel. Generated palettes work on the image's color histogram, so cost
+// grows with the number of distinct colors rather than with resolution.
+pub const MAX_PALETTE: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub colors: Vec<[u8; 4]>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<[u8; 4]>) -> Palette {
+        assert!(!colors.is_empty() && colors.len() <= MAX_PALETTE,
+                "a palette needs between 1 and {} colors", MAX_PALETTE);
+        Palette{colors}
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    // Index of the closest entry (squared RGBA distance)
+    pub fn nearest(&self, color: [f32; 4]) -> usize {
+        let mut best = (0, f32::MAX);
+        for (i, c) in self.colors.iter().enumerate() {
+            let d = distance(color, to_f32(*c));
+            if d < best.1 {best = (i, d);}
+        }
+        best.0
+    }
+
+    // Repeatedly splits the box with the widest channel range at its median
+    pub fn median_cut(image: &RgbaImage, size: usize) -> Palette {
+        let size = size.clamp(1, MAX_PALETTE);
+        let mut boxes = vec![histogram(image)];
+        while boxes.len() < size {
+            let widest = boxes.iter()
+                              .enumerate()
+                              .filter(|(_, b)| b.len() > 1)
+                              .max_by_key(|(_, b)| widest_channel(b).1)
+                              .map(|(i, _)| i);
+            let index = match widest {Some(i) => i, None => break};
+            let mut bucket = boxes.swap_remove(index);
+            let (channel, _) = widest_channel(&bucket);
+            bucket.sort_by_key(|(c, _)| c[channel]);
+
+            // Weighted median, keeping at least one color on each side
+            let total: u64 = bucket.iter().map(|(_, n)| *n as u64).sum();
+            let mut seen = 0;
+            let mut split = 1;
+            for (i, (_, n)) in bucket.iter().enumerate() {
+                seen += *n as u64;
+                if seen * 2 >= total {split = (i + 1).clamp(1, bucket.len() - 1); break;}
+            }
+            let upper = bucket.split_off(split);
+            boxes.push(bucket);
+            boxes.push(upper);
+        }
+        Palette::new(boxes.iter().map(|b| mean(b.iter().map(|(c, n)| (to_f32(*c), *n)))).collect())
+    }
+
+    // Lloyd iterations seeded with the median-cut palette
+    pub fn kmeans(image: &RgbaImage, size: usize, iterations: usize) -> Palette {
+        let colors = histogram(image);
+        let mut centers: Vec<[f32; 4]> = Palette::median_cut(image, size).colors
+                                                                          .into_iter()
+                                                                          .map(to_f32)
+                                                                          .collect();
+        for _ in 0..iterations {
+            let mut sums = vec![([0.0f64; 4], 0u64); centers.len()];
+            for (color, count) in &colors {
+                let c = to_f32(*color);
+                let (k, _) = centers.iter()
+                                    .enumerate()
+                                    .map(|(k, center)| (k, distance(c, *center)))
+                                    .fold((0, f32::MAX), |best, d| if d.1 < best.1 {d} else {best});
+                for (s, v) in sums[k].0.iter_mut().zip(c.iter()) {*s += *v as f64 * *count as f64;}
+                sums[k].1 += *count as u64;
+            }
+            let mut moved = false;
+            for (center, (sum, count)) in centers.iter_mut().zip(sums) {
+                if count == 0 {continue;}
+                let next = sum.map(|s| (s / count as f64) as f32);
+                moved |= distance(next, *center) > 0.25;
+                *center = next;
+            }
+            if !moved {break;}
+        }
+        Palette::new(centers.into_iter().map(to_u8).collect())
+    }
+}
+
+fn to_f32(c: [u8; 4]) -> [f32; 4] {
+    c.map(|v| v as f32)
+}
+
+fn to_u8(c: [f32; 4]) -> [u8; 4] {
+    c.map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+fn distance(a: [f32; 4], b: [f32; 4]) -> f32 {
+    (0..4).map(|i| (a[i] - b[i]) * (a[i] - b[i])).sum()
+}
+
+fn histogram(image: &RgbaImage) -> Vec<([u8; 4], u32)> {
+    let mut counts: HashMap<[u8; 4], u32> = HashMap::new();
+    for p in image.pixels() {
+        *counts.entry(p.0).or_insert(0) += 1;
+    }
+    let mut colors: Vec<_> = counts.into_iter().collect();
+    colors.sort();     // HashMap order is random; keep palettes reproducible
+    if colors.is_empty() {colors.push(([0, 0, 0, 0], 1));}
+    colors
+}
+
+fn widest_channel(bucket: &[([u8; 4], u32)]) -> (usize, u8) {
+    (0..4).map(|ch| {
+              let lo = bucket.iter().map(|(c, _)| c[ch]).min().unwrap_or(0);
+              let hi = bucket.iter().map(|(c, _)| c[ch]).max().unwrap_or(0);
+              (ch, hi - lo)
+          })
+          .max_by_key(|&(ch, range)| (range, std::cmp::Reverse(ch)))
+          .unwrap()
+}
+
+fn mean<I: Iterator<Item = ([f32; 4], u32)>>(colors: I) -> [u8; 4] {
+    let mut sum = [0.0f64; 4];
+    let mut total = 0u64;
+    for (c, n) in colors {
+        for i in 0..4 {sum[i] += c[i] as f64 * n as f64;}
+        total += n as u64;
+    }
+    to_u8(sum.map(|s| (s / total.max(1) as f64) as f32))
+}
+
+/*------------------------------------------------------------------------------
+                    INDEXED IMAGES AND DITHERING (STRATEGY PATTERN)
+------------------------------------------------------------------------------*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DitherMethodEnum {
+    NONE,
+    BAYER2,
+    BAYER4,
+    BAYER8,
+    BLUENOISE,
+    FLOYDSTEINBERG,
+    ATKINSON,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedImage {
+    pub width  : u32,
+    pub height : u32,
+    pub indices: Vec<u8>,
+    pub palette: Palette,
+}
+
+impl IndexedImage {
+    pub fn index(&self, x: u32, y: u32) -> u8 {
+        self.indices[(y * self.width + x) as usize]
+    }
+
+    pub fn to_rgba(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            Rgba(self.palette.colors[self.index(x, y) as usize])
+        })
+    }
+}
+
+pub fn quantize(image  : &RgbaImage,
+                palette: &Palette,
+                method : DitherMethodEnum)
+    -> IndexedImage {
+    let (width, height) = image.dimensions();
+    let indices = match method {
+        DitherMethodEnum::NONE            => ordered(image, palette, &|_, _| 0.5, 0.0),
+        DitherMethodEnum::BAYER2          => ordered(image, palette, &|x, y| bayer(1, x, y), spread(palette)),
+        DitherMethodEnum::BAYER4          => ordered(image, palette, &|x, y| bayer(2, x, y), spread(palette)),
+        DitherMethodEnum::BAYER8          => ordered(image, palette, &|x, y| bayer(3, x, y), spread(palette)),
+        DitherMethodEnum::BLUENOISE       => ordered(image, palette, &blue_noise, spread(palette)),
+        DitherMethodEnum::FLOYDSTEINBERG  => diffuse(image, palette, &FLOYD_STEINBERG, 16.0),
+        DitherMethodEnum::ATKINSON        => diffuse(image, palette, &ATKINSON, 8.0),
+    };
+    IndexedImage{width, height, indices, palette: palette.clone()}
+}
+
+// Threshold amplitude: roughly the gap between palette levels per channel
+fn spread(palette: &Palette) -> f32 {
+    255.0 / (palette.len() as f32).cbrt().max(1.0)
+}
+
+fn ordered(image    : &RgbaImage,
+           palette  : &Palette,
+           threshold: &dyn Fn(u32, u32) -> f32,
+           spread   : f32)
+    -> Vec<u8> {
+    image.enumerate_pixels()
+         .map(|(x, y, p)| {
+             let offset = spread * (threshold(x, y) - 0.5);
+             let c = to_f32(p.0);
+             palette.nearest([c[0] + offset, c[1] + offset, c[2] + offset, c[3]]) as u8
+         })
+         .collect()
+}
+
+// Recursive Bayer matrix of size 2^level, normalised to (0, 1). The lowest
+// coordinate bits select the coarsest 2x2 pattern.
+fn bayer(level: u32, x: u32, y: u32) -> f32 {
+    let value = (0..level).fold(0, |v, bit| {
+        let bx = (x >> bit) & 1;
+        let by = (y >> bit) & 1;
+        v | (((bx ^ by) << 1) | by) << (2 * (level - 1 - bit))
+    });
+    (value as f32 + 0.5) / (1u32 << (2 * level)) as f32
+}
+
+// (dx, dy, weight) taps to the right of and below the current pixel
+const FLOYD_STEINBERG: [(i32, i32, f32); 4] = [(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)];
+const ATKINSON       : [(i32, i32, f32); 6] = [(1, 0, 1.0), (2, 0, 1.0), (-1, 1, 1.0),
+                                              (0, 1, 1.0), (1, 1, 1.0), (0, 2, 1.0)];
+
+fn diffuse(image  : &RgbaImage,
+           palette: &Palette,
+           taps   : &[(i32, i32, f32)],
+           divisor: f32)
+    -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let mut work: Vec<[f32; 4]> = image.pixels().map(|p| to_f32(p.0)).collect();
+    let mut indices = Vec::with_capacity(work.len());
+
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let old = work[(y * width as i32 + x) as usize];
+            let index = palette.nearest(old);
+            let new = to_f32(palette.colors[index]);
+            indices.push(index as u8);
+
+            for &(dx, dy, weight) in taps {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {continue;}
+                let target = &mut work[(ny * width as i32 + nx) as usize];
+                for c in 0..4 {
+                    target[c] += (old[c] - new[c]) * weight / divisor;
+                }
+            }
+        }
+    }
+    indices
+}
+
+/*------------------------------------------------------------------------------
+                    BLUE NOISE (VOID AND CLUSTER)
+------------------------------------------------------------------------------*/
+// Ulichney's void-and-cluster method on a small toroidal tile, built once and
+// then tiled over the image.
+const NOISE_SIZE : usize = 32;
+const NOISE_SIGMA: f32   = 1.5;
+
+fn blue_noise(x: u32, y: u32) -> f32 {
+    static TILE: OnceLock<Vec<f32>> = OnceLock::new();
+    let tile = TILE.get_or_init(void_and_cluster);
+    tile[(y as usize % NOISE_SIZE) * NOISE_SIZE + x as usize % NOISE_SIZE]
+}
+
+struct Energy {
+    kernel: Vec<f32>,
+    values: Vec<f32>,
+}
+
+impl Energy {
+    fn new() -> Energy {
+        let n = NOISE_SIZE as i32;
+        let kernel = (0..NOISE_SIZE * NOISE_SIZE).map(|i| {
+            let dx = (i as i32 % n).min(n - i as i32 % n) as f32;
+            let dy = (i as i32 / n).min(n - i as i32 / n) as f32;
+            (-(dx * dx + dy * dy) / (2.0 * NOISE_SIGMA * NOISE_SIGMA)).exp()
+        }).collect();
+        Energy{kernel, values: vec![0.0; NOISE_SIZE * NOISE_SIZE]}
+    }
+
+    fn splat(&mut self, at: usize, sign: f32) {
+        let (ax, ay) = (at % NOISE_SIZE, at / NOISE_SIZE);
+        for (i, v) in self.values.iter_mut().enumerate() {
+            let dx = (i % NOISE_SIZE + NOISE_SIZE - ax) % NOISE_SIZE;
+            let dy = (i / NOISE_SIZE + NOISE_SIZE - ay) % NOISE_SIZE;
+            *v += sign * self.kernel[dy * NOISE_SIZE + dx];
+        }
+    }
+
+    // Tightest cluster among set pixels, or largest void among unset ones
+    fn extreme(&self, pattern: &[bool], set: bool) -> usize {
+        let candidates = self.values.iter().enumerate().filter(|(i, _)| pattern[*i] == set);
+        if set {
+            candidates.fold((0, f32::MIN), |b, (i, &v)| if v > b.1 {(i, v)} else {b}).0
+        } else {
+            candidates.fold((0, f32::MAX), |b, (i, &v)| if v < b.1 {(i, v)} else {b}).0
+        }
+    }
+}
+
+fn void_and_cluster() -> Vec<f32> {
+    let total = NOISE_SIZE * NOISE_SIZE;
+    let mut pattern = vec![false; total];
+    let mut energy  = Energy::new();
+
+    // Sparse deterministic seed pattern
+    let mut state: u32 = 0x2545_f491;
+    let mut ones = 0;
+    while ones < total / 10 {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let i = (state >> 8) as usize % total;
+        if !pattern[i] {pattern[i] = true; energy.splat(i, 1.0); ones += 1;}
+    }
+
+    // Relax until moving the tightest cluster into the largest void is a no-op.
+    // Swaps can cycle on ties, so give up after one pass's worth of moves; the
+    // pattern is already well spread by then and the ranking below still works.
+    for _ in 0..total {
+        let cluster = energy.extreme(&pattern, true);
+        pattern[cluster] = false;
+        energy.splat(cluster, -1.0);
+        let void = energy.extreme(&pattern, false);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+        if void == cluster {break;}
+    }
+
+    let mut rank = vec![0usize; total];
+
+    // Phase 1: rank the prototype's pixels by removing tightest clusters
+    let mut p = pattern.clone();
+    let mut e = Energy{kernel: energy.kernel.clone(), values: energy.values.clone()};
+    for r in (0..ones).rev() {
+        let cluster = e.extreme(&p, true);
+        p[cluster] = false;
+        e.splat(cluster, -1.0);
+        rank[cluster] = r;
+    }
+
+    // Phase 2 and 3: fill the largest voids until every pixel has a rank
+    for r in ones..total {
+        let void = energy.extreme(&pattern, false);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+        rank[void] = r;
+    }
+
+    rank.into_iter().map(|r| (r as f32 + 0.5) / total as f32).collect()
+}
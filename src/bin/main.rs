@@ -60,14 +60,14 @@ fn main() {
     // Triangle Tests
     triangle(Vec2i{x: Scalar::<i32>{value: 10}, y: Scalar::<i32>{value: 70}},
              Vec2i{x: Scalar::<i32>{value: 50}, y: Scalar::<i32>{value: 160}},
-             Vec2i{x: Scalar::<i32>{value: 70}, y: Scalar::<i32>{value: 80}}, RED, &mut img);
+             Vec2i{x: Scalar::<i32>{value: 70}, y: Scalar::<i32>{value: 80}}, RED, &mut img, TriangleMethodEnum::SCANLINE);
 
 
     imageops::flip_vertical_in_place(&mut img);
     //save image
     let path = Path::new("out/triangleTest.png");
-    let _file = match File::create(&path) {
+    match File::create(path) {
         Err(e) =>{panic!("there was a problem creating the file: {:?}", e);}
-        Ok(_file) => {let _ = img.save(&path).unwrap();}
+        Ok(_file) => {img.save(path).unwrap();}
     };
 }
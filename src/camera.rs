@@ -0,0 +1,65 @@
+use crate::backend::geometry::*;
+
+/*------------------------------------------------------------------------------
+                    PERSPECTIVE CAMERA
+------------------------------------------------------------------------------*/
+// Right-handed look-at camera. View space has x to the right, y up and z the
+// distance in front of the eye. Screen space has y down, like images, and a
+// depth in [0, 1] from `near` to `far` that is linear in screen space (it
+// follows 1 / z), so it can be interpolated across a rasterized span.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    pub eye   : Vec3f,
+    pub target: Vec3f,
+    pub up    : Vec3f,
+    // Vertical field of view in radians
+    pub fov_y : f32,
+    pub near  : f32,
+    pub far   : f32,
+}
+
+fn vec3(x: f32, y: f32, z: f32) -> Vec3f {
+    Vec3f::new(Scalar::new(x), Scalar::new(y), Scalar::new(z))
+}
+
+impl Camera {
+    pub fn look_at(eye: Vec3f, target: Vec3f, up: Vec3f) -> Camera {
+        Camera{eye, target, up, fov_y: 60f32.to_radians(), near: 0.1, far: 100.0}
+    }
+
+    // (right, up, forward) unit vectors
+    pub fn basis(&self) -> (Vec3f, Vec3f, Vec3f) {
+        let forward = (self.target - self.eye).normalize();
+        let right   = forward.cross(&self.up).normalize();
+        (right, right.cross(&forward), forward)
+    }
+
+    pub fn to_view(&self, p: Vec3f) -> Vec3f {
+        let (right, up, forward) = self.basis();
+        let d = p - self.eye;
+        vec3(d.dot(&right), d.dot(&up), d.dot(&forward))
+    }
+
+    // Screen position and depth of a view-space point in front of the eye
+    pub fn project_view(&self, v: Vec3f, width: u32, height: u32) -> Vec3f {
+        let focal  = 1.0 / (self.fov_y * 0.5).tan();
+        let aspect = width as f32 / height as f32;
+        let z = v.z.value;
+        let x = v.x.value / z * focal / aspect;
+        let y = v.y.value / z * focal;
+        vec3((x + 1.0) * 0.5 * width as f32, (1.0 - y) * 0.5 * height as f32, self.depth(z))
+    }
+
+    // Screen depth of a point `z` in front of the eye
+    pub fn depth(&self, z: f32) -> f32 {
+        (1.0 / self.near - 1.0 / z) / (1.0 / self.near - 1.0 / self.far)
+    }
+
+    // None for points outside the near and far planes
+    pub fn project(&self, p: Vec3f, width: u32, height: u32) -> Option<Vec3f> {
+        let v = self.to_view(p);
+        if v.z.value < self.near || v.z.value > self.far {return None;}
+        Some(self.project_view(v, width, height))
+    }
+}
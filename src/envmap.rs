@@ -0,0 +1,165 @@
+use std::f32::consts::PI;
+use image::{RgbaImage, Rgba};
+use crate::{texture::{Texture, TextureError, Sampler, WrapEnum},
+            backend::geometry::*};
+
+/*------------------------------------------------------------------------------
+                    CUBE MAPS
+------------------------------------------------------------------------------*/
+// Faces follow the OpenGL layout: looking down the face axis from the center,
+// image x runs along `right` and image y along `down` as given by `basis`.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CubeFaceEnum {
+    POSX,
+    NEGX,
+    POSY,
+    NEGY,
+    POSZ,
+    NEGZ,
+}
+
+pub const CUBE_FACES: [CubeFaceEnum; 6] = [CubeFaceEnum::POSX, CubeFaceEnum::NEGX,
+                                           CubeFaceEnum::POSY, CubeFaceEnum::NEGY,
+                                           CubeFaceEnum::POSZ, CubeFaceEnum::NEGZ];
+
+fn vec3(x: f32, y: f32, z: f32) -> Vec3f {
+    Vec3f::new(Scalar::new(x), Scalar::new(y), Scalar::new(z))
+}
+
+impl CubeFaceEnum {
+    // (forward, right, down) unit vectors of the face
+    pub fn basis(&self) -> (Vec3f, Vec3f, Vec3f) {
+        match self {
+            CubeFaceEnum::POSX => (vec3( 1.0, 0.0, 0.0), vec3(0.0, 0.0, -1.0), vec3(0.0, -1.0, 0.0)),
+            CubeFaceEnum::NEGX => (vec3(-1.0, 0.0, 0.0), vec3(0.0, 0.0,  1.0), vec3(0.0, -1.0, 0.0)),
+            CubeFaceEnum::POSY => (vec3(0.0,  1.0, 0.0), vec3(1.0, 0.0,  0.0), vec3(0.0, 0.0,  1.0)),
+            CubeFaceEnum::NEGY => (vec3(0.0, -1.0, 0.0), vec3(1.0, 0.0,  0.0), vec3(0.0, 0.0, -1.0)),
+            CubeFaceEnum::POSZ => (vec3(0.0, 0.0,  1.0), vec3( 1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+            CubeFaceEnum::NEGZ => (vec3(0.0, 0.0, -1.0), vec3(-1.0, 0.0, 0.0), vec3(0.0, -1.0, 0.0)),
+        }
+    }
+
+    // Direction through face coordinates (u, v) in [0, 1]
+    pub fn direction(&self, u: f32, v: f32) -> Vec3f {
+        let (forward, right, down) = self.basis();
+        (forward + right * Scalar::new(2.0 * u - 1.0) + down * Scalar::new(2.0 * v - 1.0)).normalize()
+    }
+
+    // Face hit by `dir` and the (u, v) coordinates on it
+    pub fn from_direction(dir: Vec3f) -> (CubeFaceEnum, f32, f32) {
+        let (x, y, z) = (dir.x.value, dir.y.value, dir.z.value);
+        let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+        let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+            if x > 0.0 {(CubeFaceEnum::POSX, -z, -y, ax)} else {(CubeFaceEnum::NEGX, z, -y, ax)}
+        } else if ay >= az {
+            if y > 0.0 {(CubeFaceEnum::POSY, x, z, ay)} else {(CubeFaceEnum::NEGY, x, -z, ay)}
+        } else if z > 0.0 {(CubeFaceEnum::POSZ, x, -y, az)} else {(CubeFaceEnum::NEGZ, -x, -y, az)};
+        let ma = ma.max(f32::MIN_POSITIVE);
+        (face, (sc / ma + 1.0) * 0.5, (tc / ma + 1.0) * 0.5)
+    }
+
+    fn index(&self) -> usize {
+        CUBE_FACES.iter().position(|f| f == self).unwrap()
+    }
+}
+
+pub struct CubeMap {
+    faces: Vec<Texture>,
+}
+
+impl CubeMap {
+    // Faces in `CUBE_FACES` order; all must be square, non-empty and the same size
+    pub fn new(faces: [RgbaImage; 6]) -> Result<CubeMap, TextureError> {
+        let size = faces[0].dimensions();
+        if size.0 != size.1 || faces.iter().any(|f| f.dimensions() != size) {
+            return Err(TextureError::CubeFaces);
+        }
+        let faces = Vec::from(faces).into_iter().map(Texture::new).collect::<Result<_, _>>()?;
+        Ok(CubeMap{faces})
+    }
+
+    pub fn from_equirectangular(panorama : &RgbaImage,
+                                face_size: u32,
+                                sampler  : &Sampler)
+        -> Result<CubeMap, TextureError> {
+        let panorama = Texture::new(panorama.clone())?;
+        let sampler  = Sampler{wrap_u: WrapEnum::REPEAT, wrap_v: WrapEnum::CLAMP, ..*sampler};
+        CubeMap::new(CUBE_FACES.map(|face| {
+            RgbaImage::from_fn(face_size, face_size, |x, y| {
+                let dir = face.direction((x as f32 + 0.5) / face_size as f32,
+                                         (y as f32 + 0.5) / face_size as f32);
+                Rgba(sampler.sample(&panorama, equirectangular_uv(dir)))
+            })
+        }))
+    }
+
+    // Calls `render` once per face with an empty face image and the face,
+    // whose `basis` gives the 90 degree view to draw.
+    pub fn render(face_size: u32, render: &mut dyn FnMut(CubeFaceEnum, &mut RgbaImage))
+        -> Result<CubeMap, TextureError> {
+        CubeMap::new(CUBE_FACES.map(|face| {
+            let mut image = RgbaImage::new(face_size, face_size);
+            render(face, &mut image);
+            image
+        }))
+    }
+
+    pub fn face_size(&self) -> u32 {
+        self.faces[0].width()
+    }
+
+    pub fn face(&self, face: CubeFaceEnum) -> &RgbaImage {
+        self.faces[face.index()].level(0)
+    }
+
+    // Faces are sampled with clamped edges so lookups never bleed across seams
+    pub fn sample(&self, sampler: &Sampler, dir: Vec3f) -> [u8; 4] {
+        let (face, u, v) = CubeFaceEnum::from_direction(dir);
+        let sampler = Sampler{wrap_u: WrapEnum::CLAMP, wrap_v: WrapEnum::CLAMP, ..*sampler};
+        sampler.sample(&self.faces[face.index()], Vec2f::new(Scalar::new(u), Scalar::new(v)))
+    }
+
+    // Environment reflection: mirrors `incident` about `normal` and looks it up
+    pub fn reflect(&self, sampler: &Sampler, incident: Vec3f, normal: Vec3f) -> [u8; 4] {
+        let n = normal.normalize();
+        let r = incident - n * Scalar::new(2.0 * incident.dot(&n));
+        self.sample(sampler, r)
+    }
+
+    pub fn to_equirectangular(&self, width: u32, height: u32, sampler: &Sampler) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            let dir = equirectangular_direction((x as f32 + 0.5) / width as f32,
+                                                (y as f32 + 0.5) / height as f32);
+            Rgba(self.sample(sampler, dir))
+        })
+    }
+}
+
+/*------------------------------------------------------------------------------
+                    EQUIRECTANGULAR PANORAMAS
+------------------------------------------------------------------------------*/
+// u covers longitude from -180 to 180 degrees with u = 0.5 looking down +Z;
+// v covers latitude from +90 (top row) to -90 degrees.
+pub fn equirectangular_direction(u: f32, v: f32) -> Vec3f {
+    let lon = (u - 0.5) * 2.0 * PI;
+    let lat = (0.5 - v) * PI;
+    vec3(lat.cos() * lon.sin(), lat.sin(), lat.cos() * lon.cos())
+}
+
+pub fn equirectangular_uv(dir: Vec3f) -> Vec2f {
+    let d = dir.normalize();
+    let lon = d.x.value.atan2(d.z.value);
+    let lat = d.y.value.clamp(-1.0, 1.0).asin();
+    Vec2f::new(Scalar::new(lon / (2.0 * PI) + 0.5), Scalar::new(0.5 - lat / PI))
+}
+
+// Renders the scene into a cube map and unwraps it into a 360 degree panorama
+pub fn render_equirectangular(face_size: u32,
+                              width    : u32,
+                              height   : u32,
+                              sampler  : &Sampler,
+                              render   : &mut dyn FnMut(CubeFaceEnum, &mut RgbaImage))
+    -> Result<RgbaImage, TextureError> {
+    Ok(CubeMap::render(face_size, render)?.to_equirectangular(width, height, sampler))
+}
@@ -12,4 +12,14 @@ use image::{RgbaImage, ImageBuffer, imageops};
 
 pub mod line;
 pub mod model;
-pub mod backend;
\ No newline at end of file
+pub mod backend;
+pub mod tiles;
+pub mod ssaa;
+pub mod msaa;
+pub mod texture;
+pub mod envmap;
+pub mod stroke;
+pub mod path;
+pub mod camera;
+pub mod shapes;
+pub mod render;
\ No newline at end of file
@@ -1,6 +1,103 @@
 use std::mem;
 use image::RgbaImage;
-use crate::backend::{images::set, geometry::*};
+use crate::{camera::Camera,
+            backend::{images::{set_coverage, srgb_to_linear, linear_to_srgb, DepthBuffer}, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    LINE CLIPPING
+------------------------------------------------------------------------------*/
+// Pixel rectangle with inclusive minimum and exclusive maximum, like image
+// dimensions. Rasterizers use it to skip the parts of a segment that cannot
+// reach the rectangle and never plot outside it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClipRect {
+    pub x0: i32,
+    pub y0: i32,
+    pub x1: i32,
+    pub y1: i32,
+}
+
+impl ClipRect {
+    pub fn new(x0: i32, y0: i32, x1: i32, y1: i32) -> ClipRect {
+        ClipRect{x0, y0, x1, y1}
+    }
+
+    pub fn from_image(img: &RgbaImage) -> ClipRect {
+        ClipRect::new(0, 0, img.width() as i32, img.height() as i32)
+    }
+
+    pub fn intersect(&self, other: &ClipRect) -> ClipRect {
+        ClipRect::new(self.x0.max(other.x0), self.y0.max(other.y0),
+                      self.x1.min(other.x1), self.y1.min(other.y1))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.x0 >= self.x1 || self.y0 >= self.y1
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x0 && y >= self.y0 && x < self.x1 && y < self.y1
+    }
+
+    // Liang-Barsky: the part of the segment inside the rectangle of pixel
+    // centers, or None if it misses entirely
+    pub fn clip_segment(&self, p0: Vec2f, p1: Vec2f) -> Option<(Vec2f, Vec2f)> {
+        let (t0, t1) = liang_barsky((p0.x.value as f64, p0.y.value as f64),
+                                    (p1.x.value as f64, p1.y.value as f64),
+                                    (self.x0 as f64, self.y0 as f64),
+                                    ((self.x1 - 1) as f64, (self.y1 - 1) as f64))?;
+        let d = p1 - p0;
+        Some((p0 + d * Scalar::new(t0 as f32), p0 + d * Scalar::new(t1 as f32)))
+    }
+}
+
+// Parametric range [t0, t1] of p0 + t (p1 - p0) inside [min, max]
+fn liang_barsky(p0 : (f64, f64),
+                p1 : (f64, f64),
+                min: (f64, f64),
+                max: (f64, f64))
+    -> Option<(f64, f64)> {
+    let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for &(p, q) in &[(-dx, p0.0 - min.0), (dx, max.0 - p0.0),
+                     (-dy, p0.1 - min.1), (dy, max.1 - p0.1)] {
+        if p == 0.0 {
+            if q < 0.0 {return None;}    // parallel to and outside this edge
+        } else {
+            let r = q / p;
+            if p < 0.0 {t0 = t0.max(r);} else {t1 = t1.min(r);}
+        }
+    }
+    if t0 > t1 {None} else {Some((t0, t1))}
+}
+
+// Major-axis values of a transposed, left-to-right segment that can land in
+// `clip`, padded by a pixel on each side to absorb rounding. Always a
+// subrange of [x0t, x1t).
+fn major_range(x0t  : i32,
+               y0t  : i32,
+               x1t  : i32,
+               y1t  : i32,
+               steep: bool,
+               clip : &ClipRect)
+    -> (i32, i32) {
+    let (min, max) = if steep {((clip.y0, clip.x0), (clip.y1, clip.x1))}
+                     else {((clip.x0, clip.y0), (clip.x1, clip.y1))};
+    let range = liang_barsky((x0t as f64, y0t as f64),
+                             (x1t as f64, y1t as f64),
+                             ((min.0 - 1) as f64, (min.1 - 1) as f64),
+                             (max.0 as f64, max.1 as f64));
+    match range {
+        None => (x0t, x0t),
+        Some((t0, t1)) => {
+            let dx = x1t as f64 - x0t as f64;
+            let lo = ((x0t as f64 + t0 * dx).floor() as i64 - 1).max(x0t as i64) as i32;
+            let hi = ((x0t as f64 + t1 * dx).ceil() as i64 + 2).min(x1t as i64) as i32;
+            (lo, hi.max(lo))
+        }
+    }
+}
 
 /*------------------------------------------------------------------------------
                     LINE METHODS (STRATEGY PATTERN)
@@ -10,87 +107,113 @@ struct Line <'a> {
     p1: Vec2i,
     color: [u8; 4],
     img  : &'a mut RgbaImage,
-    draw_behavior: Box<dyn DrawBehavior>,
+    clip : ClipRect,
+    draw_behavior: &'a dyn DrawBehavior,
 }
 
-trait DrawBehavior {
+// Rasterizers emit pixel coordinates and their coverage (1.0 for solid
+// pixels) through `plot` instead of writing to an image, so the same
+// algorithm can target a whole image or a single tile. `clip` lets them skip
+// steps that cannot reach the visible region; the caller still discards any
+// pixel outside it. A pixel span is simply a run of `plot` calls.
+//
+// The built-in methods below are ordinary implementations, picked by
+// `LineMethodEnum`. Every function taking a line method accepts either that
+// enum or a reference to any other implementation, such as `&MyMethod`.
+// Implementations must be `Send + Sync` so they can be shared across threads.
+pub trait DrawBehavior: Send + Sync {
     fn draw(&self,
-            p0: Vec2i,
-            p1: Vec2i,
-            color: [u8; 4],
-            img  : &mut RgbaImage);
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32));
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum LineMethodEnum {
     NAIVE0,
     NAIVE1,
     NAIVE2,
     BRESENHAM,
+    WU,
+    DDA,
+    EFLA,
+    DOUBLESTEP,
+    RUNSLICE,
 }
 
-struct Naive0{}
-struct Naive1{}
-struct Naive2{}
-struct Bresenham{}
-struct Wu{}
+pub struct Naive0;
+pub struct Naive1;
+pub struct Naive2;
+pub struct Bresenham;
+pub struct Wu;
+pub struct Dda;
+pub struct Efla;
+pub struct DoubleStep;
+pub struct RunSlice;
 
 impl DrawBehavior for Naive0 {
     fn draw(&self,
-            p0: Vec2i,
-            p1: Vec2i,
-            color: [u8; 4],
-            img  : &mut RgbaImage) {
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
         for t in 0..100 {
             let t = t as f32 * 0.01;
-            let x: i32 = (p0.x.value as f32 + (p1.x.value - p0.x.value) as f32 * t) as i32;
-            let y: i32 = (p0.y.value as f32 + (p1.y.value - p0.y.value) as f32 * t) as i32;
-            set(img, x, y, color);
+            let x: i32 = (p0.x.value as f32 + (p1.x.value as f32 - p0.x.value as f32) * t) as i32;
+            let y: i32 = (p0.y.value as f32 + (p1.y.value as f32 - p0.y.value as f32) * t) as i32;
+            if clip.contains(x, y) {plot(x, y, 1.0);}
         }
     }
 }
 
 impl DrawBehavior for Naive1 {
     fn draw(&self,
-            p0   : Vec2i,
-            p1   : Vec2i,
-            color: [u8; 4],
-            img  : &mut RgbaImage) {
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
         let mut steep: bool = false;
         let mut x0t = p0.x.value;
         let mut x1t = p1.x.value;
         let mut y0t = p0.y.value;
         let mut y1t = p1.y.value;
 
-        if (p0.x.value - p1.x.value).abs() < (p0.y.value - p1.y.value).abs() { //if the line is steep, transpose
+        if (p0.x.value as i64 - p1.x.value as i64).abs() < (p0.y.value as i64 - p1.y.value as i64).abs() { //if the line is steep, transpose
             mem::swap(&mut x0t, &mut y0t);
             mem::swap(&mut x1t, &mut y1t);
             steep = true;
         }
 
-        if p0.x.value > p1.x.value { // make it left to right
+        if x0t > x1t { // make it left to right
             mem::swap(&mut x0t, &mut x1t);
             mem::swap(&mut y0t, &mut y1t);
         }
 
-        for x in x0t..x1t  {
-            let t: f32 = (x-x0t) as f32 / (x1t-x0t) as f32;
-            let y: i32 = (y0t as f32 * (1.0 - t) + y1t as f32 * t) as i32;
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        for x in xa..xb  {
+            // f32 would put rows of far-apart endpoints pixels off the line,
+            // outside the span `major_range` keeps
+            let t: f64 = (x as i64 - x0t as i64) as f64 / (x1t as i64 - x0t as i64) as f64;
+            let y: i32 = (y0t as f64 * (1.0 - t) + y1t as f64 * t) as i32;
 
             if steep {
-                set(img, y, x, color);  //if transposed, de-transpose
+                plot(y, x, 1.0);  //if transposed, de-transpose
             } else {
-                set(img, x, y, color);
+                plot(x, y, 1.0);
             }
         }
     }
 }
 
+const NAIVE2_MAX_HEAD: i64 = 1 << 16;
+
 impl DrawBehavior for Naive2 {
     fn draw(&self,
-            p0   : Vec2i,
-            p1   : Vec2i,
-            color: [u8; 4],
-            img  : &mut RgbaImage) {
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
 
         let mut steep: bool = false;
         let mut x0t = p0.x.value;
@@ -98,28 +221,40 @@ impl DrawBehavior for Naive2 {
         let mut y0t = p0.y.value;
         let mut y1t = p1.y.value;
 
-        if (p0.x.value - p1.x.value).abs() < (p0.y.value - p1.y.value).abs() { //if the line is steep, transpose
+        if (p0.x.value as i64 - p1.x.value as i64).abs() < (p0.y.value as i64 - p1.y.value as i64).abs() { //if the line is steep, transpose
             mem::swap(&mut x0t, &mut y0t);
             mem::swap(&mut x1t, &mut y1t);
             steep = true;
         }
 
-        if p0.x.value > p1.x.value { // make it left to right { // make it left to right
+        if x0t > x1t { // make it left to right
             mem::swap(&mut x0t, &mut x1t);
             mem::swap(&mut y0t, &mut y1t);
         }
 
-        let dx:        i32 = x1t - x0t;
-        let dy:        i32 = y1t - y0t;
+        let dx:        i64 = x1t as i64 - x0t as i64;
+        let dy:        i64 = y1t as i64 - y0t as i64;
         let derror:    f32 = (dy as f32/ dx as f32).abs();
-        let mut error: f32 = 0.;
-        let mut y:     i32 = y0t;
 
-        for x in x0t..x1t  {
-            if steep {
-                set(img, y, x, color);  //if transposed, de-transpose
-            } else {
-                set(img, x, y, color);
+        // The float error is accumulated from the start so a clipped line
+        // stays identical to the unclipped one. Only a start more than
+        // NAIVE2_MAX_HEAD steps away is skipped in closed form (the error stays
+        // in (-0.5, 0.5], so after k steps y has moved ceil(k * derror - 0.5)
+        // times), which may round exact halves differently.
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        let start = if xa as i64 - x0t as i64 > NAIVE2_MAX_HEAD {xa} else {x0t};
+        let grown: f64 = (start as i64 - x0t as i64) as f64 * derror as f64;
+        let moves: f64 = (grown - 0.5).ceil().max(0.0);
+        let mut error: f32 = (grown - moves) as f32;
+        let mut y:     i32 = (y0t as i64 + if y1t > y0t {moves as i64} else {-(moves as i64)}) as i32;
+
+        for x in start..xb  {
+            if x >= xa {
+                if steep {
+                    plot(y, x, 1.0);  //if transposed, de-transpose
+                } else {
+                    plot(x, y, 1.0);
+                }
             }
 
             error += derror;
@@ -133,10 +268,10 @@ impl DrawBehavior for Naive2 {
 
 impl DrawBehavior for Bresenham {
     fn draw(&self,
-            p0   : Vec2i,
-            p1   : Vec2i,
-            color: [u8; 4],
-            img  : &mut RgbaImage) {
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
         // Needed for mutability and protection of user input
         let mut steep: bool = false;
         let mut x0t = p0.x.value;
@@ -144,26 +279,34 @@ impl DrawBehavior for Bresenham {
         let mut y0t = p0.y.value;
         let mut y1t = p1.y.value;
 
-        if (p0.x.value - p1.x.value).abs() < (p0.y.value - p1.y.value).abs() { //if the line is steep, transpose
+        if (p0.x.value as i64 - p1.x.value as i64).abs() < (p0.y.value as i64 - p1.y.value as i64).abs() { //if the line is steep, transpose
             mem::swap(&mut x0t, &mut y0t);
             mem::swap(&mut x1t, &mut y1t);
             steep = true;
         }
 
-        if p0.x.value > p1.x.value { // make it left to right
+        if x0t > x1t { // make it left to right
             mem::swap(&mut x0t, &mut x1t);
             mem::swap(&mut y0t, &mut y1t);
         }
 
-        let dx:        i32 = x1t - x0t;
-        let dy:        i32 = y1t - y0t;
-        let derror:    i32 = dy.abs() * 2;
-        let mut error: i32 = 0;
-        let mut y:     i32 = y0t;
+        // Deltas of far-apart endpoints overflow i32
+        let dx:        i64 = x1t as i64 - x0t as i64;
+        let dy:        i64 = y1t as i64 - y0t as i64;
+        let derror:    i64 = dy.abs() * 2;
+
+        // Jump straight to the first visible step: after k steps the error has
+        // grown by k * derror and y has moved once for every time it passed dx
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        let k:     i128 = xa as i128 - x0t as i128;
+        let grown: i128 = k * derror as i128;
+        let moves: i128 = if grown > dx as i128 {(grown + dx as i128 - 1) / (2 * dx as i128)} else {0};
+        let mut error: i64 = (grown - moves * 2 * dx as i128) as i64;
+        let mut y:     i32 = (y0t as i128 + if y1t > y0t {moves} else {-moves}) as i32;
 
         if steep {
-            for x in x0t..x1t  {
-                set(img, y, x, color);  //if transposed, de-transpose
+            for x in xa..xb  {
+                plot(y, x, 1.0);  //if transposed, de-transpose
                 error += derror;
                 if error > dx {
                     y += if y1t > y0t {1} else {-1};
@@ -171,8 +314,8 @@ impl DrawBehavior for Bresenham {
                 }
             }
         } else {
-            for x in x0t..x1t  {
-                set(img, x, y, color);
+            for x in xa..xb  {
+                plot(x, y, 1.0);
                 error += derror;
                 if error > dx {
                     y += if y1t > y0t {1} else {-1};
@@ -184,61 +327,697 @@ impl DrawBehavior for Bresenham {
 }
 
 
-impl Line<'_> {
+// Xiaolin Wu: each step along the major axis splits coverage between the two
+// pixels straddling the ideal line. Endpoints are weighted by how much of
+// their pixel the segment actually spans.
+impl DrawBehavior for Wu {
+    fn draw(&self,
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
+        // f64 keeps the slope and the row positions exact enough for
+        // endpoints anywhere in the i32 range
+        let fpart  = |v: f64| (v - v.floor()) as f32;
+        let rfpart = |v: f64| 1.0 - fpart(v);
+
+        let mut x0t = p0.x.value as f64;
+        let mut x1t = p1.x.value as f64;
+        let mut y0t = p0.y.value as f64;
+        let mut y1t = p1.y.value as f64;
+
+        let steep = (y1t - y0t).abs() > (x1t - x0t).abs();
+        if steep { //if the line is steep, transpose
+            mem::swap(&mut x0t, &mut y0t);
+            mem::swap(&mut x1t, &mut y1t);
+        }
+        if x0t > x1t { // make it left to right
+            mem::swap(&mut x0t, &mut x1t);
+            mem::swap(&mut y0t, &mut y1t);
+        }
+
+        // The pixel past an endpoint at the edge of the i32 range does not exist
+        let mut put = |x: i32, y: i64, c: f32| {
+            if y < i32::MIN as i64 || y > i32::MAX as i64 {return;}
+            let y = y as i32;
+            if steep {plot(y, x, c)} else {plot(x, y, c)}  //if transposed, de-transpose
+        };
+
+        let dx = x1t - x0t;
+        let dy = y1t - y0t;
+        let gradient = if dx == 0.0 {1.0} else {dy / dx};
+
+        // first endpoint
+        let xend  = x0t.round();
+        let yend  = y0t + gradient * (xend - x0t);
+        let xgap  = rfpart(x0t + 0.5);
+        let xpxl1 = xend as i32;
+        let ypxl1 = yend.floor() as i64;
+        put(xpxl1, ypxl1,     rfpart(yend) * xgap);
+        put(xpxl1, ypxl1 + 1, fpart(yend)  * xgap);
+        let intery = |x: i32| yend + gradient * (x as i64 - xpxl1 as i64) as f64;
+
+        // second endpoint
+        let xend  = x1t.round();
+        let yend  = y1t + gradient * (xend - x1t);
+        let xgap  = fpart(x1t + 0.5);
+        let xpxl2 = xend as i32;
+        let ypxl2 = yend.floor() as i64;
+        if xpxl2 == xpxl1 {return;}
+        put(xpxl2, ypxl2,     rfpart(yend) * xgap);
+        put(xpxl2, ypxl2 + 1, fpart(yend)  * xgap);
+
+        let (xa, xb) = major_range(xpxl1, ypxl1 as i32, xpxl2, ypxl2 as i32, steep, clip);
+        for x in xa.max(xpxl1 + 1)..xb {
+            put(x, intery(x).floor() as i64,     rfpart(intery(x)));
+            put(x, intery(x).floor() as i64 + 1, fpart(intery(x)));
+        }
+    }
+}
+
+// The methods below work on the segment transposed so x is the major axis and
+// ordered left to right, like Bresenham, and light the same pixels: row
+// y0t + round(k * dy / dx) at column x0t + k for k in 0..dx, with exact
+// halves rounded toward y0t. Deltas and offsets are taken in i64 (or wider)
+// since far-apart endpoints overflow i32.
+fn transposed(p0: Vec2i, p1: Vec2i) -> (i32, i32, i32, i32, bool) {
+    let (mut x0t, mut y0t, mut x1t, mut y1t) = (p0.x.value, p0.y.value, p1.x.value, p1.y.value);
+    let steep = (x1t as i64 - x0t as i64).abs() < (y1t as i64 - y0t as i64).abs();
+    if steep {
+        mem::swap(&mut x0t, &mut y0t);
+        mem::swap(&mut x1t, &mut y1t);
+    }
+    if x0t > x1t {
+        mem::swap(&mut x0t, &mut x1t);
+        mem::swap(&mut y0t, &mut y1t);
+    }
+    (x0t, y0t, x1t, y1t, steep)
+}
+
+// Floating-point digital differential analyzer: y advances by the slope every
+// column and is rounded. The tiny bias keeps float error from flipping exact
+// halves.
+impl DrawBehavior for Dda {
+    fn draw(&self,
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
+        let (x0t, y0t, x1t, y1t, steep) = transposed(p0, p1);
+        if x1t == x0t {return;}
+        let slope = (y1t as i64 - y0t as i64) as f64 / (x1t as i64 - x0t as i64) as f64;
+        let sign: i64 = if slope < 0.0 {-1} else {1};
+
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        let mut offset = (xa as i64 - x0t as i64) as f64 * slope.abs();
+        for x in xa..xb {
+            let y = (y0t as i64 + sign * (offset - 0.5 - 1e-9).ceil() as i64) as i32;
+            if steep {plot(y, x, 1.0);} else {plot(x, y, 1.0);}
+            offset += slope.abs();
+        }
+    }
+}
+
+// Extremely Fast Line Algorithm (Po-Han Lin): one fixed-point add per pixel
+// and no branches. The original uses 16.16 fixed point; 32 fraction bits
+// keep it exact for any segment that fits an image.
+impl DrawBehavior for Efla {
+    fn draw(&self,
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
+        let (x0t, y0t, x1t, y1t, steep) = transposed(p0, p1);
+        let dx = x1t as i64 - x0t as i64;
+        if dx == 0 {return;}
+        let dy   = y1t as i64 - y0t as i64;
+        let sign = dy.signum();
+        let increment: i128 = ((dy.abs() as i128) << 32) / dx as i128;
+
+        // Start from the exact position at the first visible column, less
+        // one unit so halves round down
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        let k = xa as i128 - x0t as i128;
+        let mut j: i128 = ((k * dy.abs() as i128) << 32) / dx as i128 + (1 << 31) - 1;
+        for x in xa..xb {
+            let y = (y0t as i64 + sign * (j >> 32) as i64) as i32;
+            if steep {plot(y, x, 1.0);} else {plot(x, y, 1.0);}
+            j += increment;
+        }
+    }
+}
+
+// Symmetric double-step midpoint (Wu and Rokne): walks in from both ends at
+// once, and each step decides two columns with at most two comparisons
+// between the three possible patterns. The far half mirrors the near one, with
+// exact halves rounded the other way. Only the pairs whose columns can reach
+// the clip rectangle are walked.
+impl DrawBehavior for DoubleStep {
+    fn draw(&self,
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
+        let (x0t, y0t, x1t, y1t, steep) = transposed(p0, p1);
+        let dx = x1t as i64 - x0t as i64;
+        if dx == 0 {return;}
+        let dy   = (y1t as i64 - y0t as i64).abs();
+        let sign = (y1t as i64 - y0t as i64).signum();
+        let mut put = |x: i64, y: i64| {
+            let (x, y) = (x as i32, y as i32);
+            if steep {plot(y, x, 1.0);} else {plot(x, y, 1.0);}
+        };
+
+        // Columns 0..=near come from the start, dx - 1 down to near + 1 from
+        // the end, as mirrors of columns 1..=far
+        let near = (dx - 1) / 2;
+        let far  = dx - 1 - near;
+        put(x0t as i64, y0t as i64);
+
+        // Visible columns x0t + k (near half) and x1t - k (far half) give the
+        // range of k to walk, started on a pair boundary
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        let (xa, xb) = (xa as i64 - x0t as i64, xb as i64 - x0t as i64);
+        let (near_lo, near_hi) = (xa.max(1), (xb - 1).min(near));
+        let (far_lo,  far_hi)  = ((dx - xb + 1).max(1), (dx - xa).min(far));
+        let (lo, hi) = match (near_lo <= near_hi, far_lo <= far_hi) {
+            (true,  true)  => (near_lo.min(far_lo), near_hi.max(far_hi)),
+            (true,  false) => (near_lo, near_hi),
+            (false, true)  => (far_lo, far_hi),
+            (false, false) => return,
+        };
+
+        // e = 2 k dy - (2 y + 1) dx, kept in (-2 dx, 0], for the column before
+        // the pair; its row is k dy / dx rounded with halves down
+        let mut k: i64 = lo - (lo - 1) % 2;
+        let before = (k - 1) as i128;
+        let mut y: i64 = ((2 * before * dy as i128 + dx as i128 - 1) / (2 * dx as i128)) as i64;
+        let mut e: i64 = (2 * before * dy as i128 - (2 * y as i128 + 1) * dx as i128) as i64;
+        while k <= far.min(hi) {
+            let (e1, e2) = (e + 2 * dy, e + 4 * dy);
+            let (y1, y2, tie1, tie2);
+            if 2 * dy < dx {
+                // Gentle: at most one step up across the pair
+                if e2 <= 0 {
+                    (y1, y2, tie1, tie2) = (y, y, false, e2 == 0);
+                    e = e2;
+                } else {
+                    let first = e1 > 0;
+                    (y1, y2) = if first {(y + 1, y + 1)} else {(y, y + 1)};
+                    (tie1, tie2) = (e1 == 0, if first {e2 - 2 * dx == 0} else {false});
+                    e = e2 - 2 * dx;
+                }
+            } else if e2 - 2 * dx > 0 {
+                // Steep: at least one step up across the pair
+                (y1, y2, tie1, tie2) = (y + 1, y + 2, false, false);
+                e = e2 - 4 * dx;
+            } else {
+                let first = e1 > 0;
+                (y1, y2) = if first {(y + 1, y + 1)} else {(y, y + 1)};
+                (tie1, tie2) = (e1 == 0, if first {e2 - 2 * dx == 0} else {false});
+                e = e2 - 2 * dx;
+            }
+
+            for (i, offset, tie) in [(k, y1, tie1), (k + 1, y2, tie2)] {
+                if i > far {break;}
+                if i <= near {put(x0t as i64 + i, y0t as i64 + sign * offset);}
+                put(x1t as i64 - i, y1t as i64 - sign * (offset + tie as i64));
+            }
+            y = y2;
+            k += 2;
+        }
+    }
+}
+
+// Run-slice Bresenham: instead of deciding every pixel, it computes how long
+// each horizontal run is (the whole part of dx / dy, plus one when the error
+// carries over) and plots the run as a span.
+impl DrawBehavior for RunSlice {
+    fn draw(&self,
+            p0  : Vec2i,
+            p1  : Vec2i,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32, f32)) {
+        let (x0t, y0t, x1t, y1t, steep) = transposed(p0, p1);
+        let dx = x1t as i64 - x0t as i64;
+        if dx == 0 {return;}
+        let dy   = (y1t as i64 - y0t as i64).abs();
+        let sign = (y1t as i64 - y0t as i64).signum();
+        let (xa, xb) = major_range(x0t, y0t, x1t, y1t, steep, clip);
+        let (ka, kb) = (xa as i64 - x0t as i64, xb as i64 - x0t as i64);
+        if ka >= kb {return;}
+        let mut span = |from: i64, to: i64, row: i64| {
+            let y = (y0t as i64 + sign * row) as i32;
+            for k in from.max(ka)..to.min(kb) {
+                let x = (x0t as i64 + k) as i32;
+                if steep {plot(y, x, 1.0);} else {plot(x, y, 1.0);}
+            }
+        };
+        if dy == 0 {
+            span(0, dx, 0);
+            return;
+        }
+
+        // Row r ends at column floor((2 r + 1) dx / (2 dy)); start at the
+        // row of the first visible column
+        let row0 = 2 * ka as i128 * dy as i128 - dx as i128;
+        let mut row: i64 = (-(-row0).div_euclid(2 * dx as i128)).max(0) as i64;
+        let num = (2 * row as i128 + 1) * dx as i128;
+        let mut end = (num / (2 * dy) as i128) as i64;
+        let mut rem = (num % (2 * dy) as i128) as i64;
+        let mut start = if row == 0 {0} else {((2 * row as i128 - 1) * dx as i128 / (2 * dy) as i128) as i64 + 1};
+        let (run, carry) = (dx / dy, 2 * (dx % dy));
+        loop {
+            span(start, end + 1, row);
+            if end + 1 >= kb {break;}
+            start = end + 1;
+            row += 1;
+            end += run;
+            rem += carry;
+            if rem >= 2 * dy {
+                end += 1;
+                rem -= 2 * dy;
+            }
+        }
+    }
+}
+
+impl LineMethodEnum {
+    pub fn behavior(self) -> &'static dyn DrawBehavior {
+        match self {
+            LineMethodEnum::NAIVE0    => &Naive0,
+            LineMethodEnum::NAIVE1    => &Naive1,
+            LineMethodEnum::NAIVE2    => &Naive2,
+            LineMethodEnum::BRESENHAM => &Bresenham,
+            LineMethodEnum::WU        => &Wu,
+            LineMethodEnum::DDA       => &Dda,
+            LineMethodEnum::EFLA      => &Efla,
+            LineMethodEnum::DOUBLESTEP=> &DoubleStep,
+            LineMethodEnum::RUNSLICE  => &RunSlice,
+        }
+    }
+}
+
+impl<'a> From<LineMethodEnum> for &'a dyn DrawBehavior {
+    fn from(line_method: LineMethodEnum) -> &'a dyn DrawBehavior {
+        line_method.behavior()
+    }
+}
+
+impl<'a, T: DrawBehavior> From<&'a T> for &'a dyn DrawBehavior {
+    fn from(draw_behavior: &'a T) -> &'a dyn DrawBehavior {
+        draw_behavior
+    }
+}
+
+impl<'a> Line<'a> {
     fn new(p0   : Vec2i,
            p1   : Vec2i,
            color: [u8; 4],
-           img  : &mut RgbaImage,
-           line_method: LineMethodEnum)
-        -> Line<'_> {
-        match line_method {
-            LineMethodEnum::NAIVE0 => Line{p0,
-                                           p1,
-                                           color,
-                                           img,
-                                           draw_behavior: Box::new(Naive0{})
-                                          },
-                                          
-            LineMethodEnum::NAIVE1 => Line{p0,
-                                           p1,
-                                           color,
-                                           img,
-                                           draw_behavior: Box::new(Naive1{})
-                                          },
-
-            LineMethodEnum::NAIVE2 => Line{p0,
-                                           p1,
-                                           color,
-                                           img,
-                                           draw_behavior: Box::new(Naive2{})
-                                          },
-
-            LineMethodEnum::BRESENHAM => Line{p0,
-                                              p1,
-                                              color,
-                                              img,
-                                              draw_behavior: Box::new(Bresenham{})
-                                          },
-        }
+           img  : &'a mut RgbaImage,
+           clip : ClipRect,
+           draw_behavior: &'a dyn DrawBehavior)
+        -> Line<'a> {
+        let clip = clip.intersect(&ClipRect::from_image(img));
+        Line{p0,
+             p1,
+             color,
+             img,
+             clip,
+             draw_behavior
+            }
     }
 
     fn draw(self) {
-        self.draw_behavior.draw(self.p0,
-                                self.p1,
-                                self.color,
-                                self.img);
-    }
-}
-
-pub fn line(p0t   : Vec2i,
-            p1t   : Vec2i,
-            colort: [u8; 4],
-            imaget: &mut RgbaImage,
-            line_method: LineMethodEnum) {
-    let mut temp = Line::new(p0t,
-                             p1t,
-                             colort,
-                             imaget,
-                             line_method);
+        let color = self.color;
+        let img   = self.img;
+        line_pixels(self.draw_behavior,
+                    self.p0,
+                    self.p1,
+                    &self.clip,
+                    &mut |x, y, coverage| {set_coverage(img, x, y, color, coverage);});
+    }
+}
+
+pub fn line<'a>(p0t   : Vec2i,
+                p1t   : Vec2i,
+                colort: [u8; 4],
+                imaget: &mut RgbaImage,
+                line_method: impl Into<&'a dyn DrawBehavior>) {
+    let clip = ClipRect::from_image(imaget);
+    line_clipped(p0t, p1t, colort, imaget, clip, line_method);
+}
+
+// Like `line`, but only pixels inside `clip` (and the image) are touched
+pub fn line_clipped<'a>(p0t   : Vec2i,
+                        p1t   : Vec2i,
+                        colort: [u8; 4],
+                        imaget: &mut RgbaImage,
+                        clip  : ClipRect,
+                        line_method: impl Into<&'a dyn DrawBehavior>) {
+    let temp = Line::new(p0t,
+                         p1t,
+                         colort,
+                         imaget,
+                         clip,
+                         line_method.into());
     temp.draw();
-}
\ No newline at end of file
+}
+
+// Draws many segments with one method lookup and no per-segment setup.
+// `colors` holds either one color per segment or a single color for all.
+pub fn lines<'a>(segments: &[(Vec2i, Vec2i)],
+                 colors  : &[[u8; 4]],
+                 imaget  : &mut RgbaImage,
+                 line_method: impl Into<&'a dyn DrawBehavior>) {
+    assert!(colors.len() == segments.len() || colors.len() == 1,
+            "lines needs one color per segment or a single color");
+    let draw_behavior = line_method.into();
+    let clip = ClipRect::from_image(imaget);
+    for (i, &(p0, p1)) in segments.iter().enumerate() {
+        let color = if colors.len() == 1 {colors[0]} else {colors[i]};
+        line_pixels(draw_behavior, p0, p1, &clip, &mut |x, y, coverage| {
+            set_coverage(imaget, x, y, color, coverage);
+        });
+    }
+}
+
+// Runs the rasterizer without an image, handing every pixel inside `clip`
+// to `plot`. Generic over `plot` so the clip test is the only closure the
+// method calls through per pixel.
+pub(crate) fn line_pixels<F>(draw_behavior: &dyn DrawBehavior,
+                             p0  : Vec2i,
+                             p1  : Vec2i,
+                             clip: &ClipRect,
+                             plot: &mut F)
+    where F: FnMut(i32, i32, f32) + ?Sized {
+    if clip.is_empty() {return;}
+    draw_behavior.draw(p0, p1, clip, &mut |x, y, coverage| {
+        if clip.contains(x, y) {plot(x, y, coverage);}
+    });
+}
+
+/*------------------------------------------------------------------------------
+                    LINE PATTERNS
+------------------------------------------------------------------------------*/
+// A pixel's place in the pattern is measured from the segment start along the
+// major axis, so both pixels of a Wu column share it. Dashes scale that to
+// the Euclidean length of the line, stipples count it in pixel steps like
+// OpenGL's glLineStipple. Polylines carry the position from one segment to
+// the next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinePatternEnum {
+    SOLID,
+    // Alternating on/off lengths in pixels, starting `phase` pixels in
+    DASH{lengths: Vec<f32>, phase: f32},
+    // Bit i (least significant first) decides pixel steps i * factor up to
+    // (i + 1) * factor, repeating every 16 bits
+    STIPPLE{bits: u16, factor: u32},
+}
+
+impl LinePatternEnum {
+    // Odd-length patterns repeat twice so on and off alternate, as in SVG
+    pub fn dash(lengths: &[f32], phase: f32) -> LinePatternEnum {
+        assert!(lengths.iter().all(|l| *l >= 0.0), "dash lengths must not be negative");
+        let mut lengths = lengths.to_vec();
+        if lengths.len() % 2 == 1 {lengths.extend_from_within(..);}
+        LinePatternEnum::DASH{lengths, phase}
+    }
+
+    pub fn stipple(bits: u16, factor: u32) -> LinePatternEnum {
+        LinePatternEnum::STIPPLE{bits, factor: factor.max(1)}
+    }
+
+    // Whether the pattern is on `distance` pixels (or pixel steps) in
+    pub fn is_on(&self, distance: f32) -> bool {
+        self.is_on_at(distance as f64)
+    }
+
+    // The same in f64, which keeps single pixels apart billions of pixels in
+    fn is_on_at(&self, distance: f64) -> bool {
+        match self {
+            LinePatternEnum::SOLID => true,
+            LinePatternEnum::DASH{lengths, phase} => {
+                let total: f64 = lengths.iter().map(|&l| l as f64).sum();
+                if total.is_nan() || total <= 0.0 {return true;}
+                let mut pos = (distance + *phase as f64).rem_euclid(total);
+                for (i, &length) in lengths.iter().enumerate() {
+                    if pos < length as f64 {return i % 2 == 0;}
+                    pos -= length as f64;
+                }
+                false
+            },
+            LinePatternEnum::STIPPLE{bits, factor} => {
+                let step = distance.max(0.0) as u64 / (*factor).max(1) as u64;
+                (bits >> (step % 16)) & 1 == 1
+            },
+        }
+    }
+
+    // How far one segment advances the pattern
+    fn advance(&self, p0: Vec2i, p1: Vec2i) -> f64 {
+        let (dx, dy) = (p1.x.value as f64 - p0.x.value as f64, p1.y.value as f64 - p0.y.value as f64);
+        match self {
+            LinePatternEnum::STIPPLE{..} => dx.abs().max(dy.abs()),
+            _                            => dx.hypot(dy),
+        }
+    }
+}
+
+pub fn line_patterned<'a>(p0t    : Vec2i,
+                          p1t    : Vec2i,
+                          colort : [u8; 4],
+                          imaget : &mut RgbaImage,
+                          line_method: impl Into<&'a dyn DrawBehavior>,
+                          pattern: &LinePatternEnum) {
+    polyline(&[p0t, p1t], colort, imaget, line_method, pattern);
+}
+
+// Connected segments through `points`. Shared vertices are drawn once and the
+// pattern runs on across them instead of restarting.
+pub fn polyline<'a>(points : &[Vec2i],
+                    colort : [u8; 4],
+                    imaget : &mut RgbaImage,
+                    line_method: impl Into<&'a dyn DrawBehavior>,
+                    pattern: &LinePatternEnum) {
+    let clip = ClipRect::from_image(imaget);
+    polyline_pixels(points, false, line_method.into(), pattern, &clip, &mut |x, y, coverage| {
+        set_coverage(imaget, x, y, colort, coverage);
+    });
+}
+
+// Pixels two segments put on their shared vertex are plotted once with the
+// coverages merged: Wu gives each segment half of its end pixel, so the halves
+// are added (capped at full coverage) and a joint is as bright as the rest of
+// the line, while solid methods still plot it once. A `closed` polyline treats
+// the first vertex as a joint too.
+pub(crate) fn polyline_pixels(points : &[Vec2i],
+                              closed : bool,
+                              draw_behavior: &dyn DrawBehavior,
+                              pattern: &LinePatternEnum,
+                              clip   : &ClipRect,
+                              plot   : &mut dyn FnMut(i32, i32, f32)) {
+    let mut offset = 0.0;
+    // Joint pixels held back until the other segment at that vertex is drawn:
+    // the previous segment's end and, when closed, the first segment's start
+    let mut joint: Vec<(i32, i32, f32)> = Vec::new();
+    let mut first: Vec<(i32, i32, f32)> = Vec::new();
+    let segments = points.len().saturating_sub(1);
+    fn merge(a: f32, b: f32) -> f32 {
+        (a + b).min(1.0)
+    }
+    fn flush(pixels: &[(i32, i32, f32)], plot: &mut dyn FnMut(i32, i32, f32)) {
+        for &(x, y, coverage) in pixels {
+            if coverage > 0.0 {plot(x, y, coverage);}
+        }
+    }
+    for (i, pair) in points.windows(2).enumerate() {
+        let (p0, p1) = (pair[0], pair[1]);
+        let (dx, dy) = (p1.x.value as i64 - p0.x.value as i64, p1.y.value as i64 - p0.y.value as i64);
+        let steep = dy.abs() > dx.abs();
+        let major = dx.abs().max(dy.abs());
+        let scale = if major > 0 {pattern.advance(p0, p1) / major as f64} else {0.0};
+        let hold_end = i + 1 < segments || (closed && i > 0);
+        let mut ends = Vec::new();
+        line_pixels(draw_behavior, p0, p1, clip, &mut |x, y, coverage| {
+            let t = if steep {(y as i64 - p0.y.value as i64).abs()} else {(x as i64 - p0.x.value as i64).abs()};
+            let coverage = if pattern.is_on_at(offset + t as f64 * scale) {coverage} else {0.0};
+            if t == 0 {
+                if let Some(held) = joint.iter_mut().find(|p| (p.0, p.1) == (x, y)) {
+                    held.2 = merge(held.2, coverage);
+                    return;
+                }
+                if closed && i == 0 && segments > 1 {
+                    first.push((x, y, coverage));
+                    return;
+                }
+            }
+            if t == major && hold_end {
+                ends.push((x, y, coverage));
+                return;
+            }
+            if coverage > 0.0 {plot(x, y, coverage);}
+        });
+        flush(&joint, plot);
+        joint = ends;
+        offset += pattern.advance(p0, p1);
+    }
+    for held in &mut first {
+        if let Some(end) = joint.iter().position(|p| (p.0, p.1) == (held.0, held.1)) {
+            held.2 = merge(held.2, joint.swap_remove(end).2);
+        }
+    }
+    flush(&joint, plot);
+    flush(&first, plot);
+}
+
+/*------------------------------------------------------------------------------
+                    GRADIENT LINES
+------------------------------------------------------------------------------*/
+// Endpoint attributes are interpolated by the pixel's position along the
+// major axis, so every method (and both pixels of a Wu column) agree.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GradientModeEnum {
+    // Blends the stored 8-bit values directly
+    LINEAR,
+    // Decodes to linear light, blends, and re-encodes, so midpoints keep
+    // their perceived brightness
+    SRGB,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LineVertex {
+    pub position: Vec2i,
+    pub color   : [u8; 4],
+    // Smaller is nearer, as in `DepthBuffer`
+    pub depth   : f32,
+}
+
+impl LineVertex {
+    pub fn new(position: Vec2i, color: [u8; 4]) -> LineVertex {
+        LineVertex{position, color, depth: 0.0}
+    }
+
+    pub fn with_depth(self, depth: f32) -> LineVertex {
+        LineVertex{depth, ..self}
+    }
+}
+
+// Color `t` of the way from `c0` to `c1`; alpha is always blended linearly
+pub fn lerp_color(c0: [u8; 4], c1: [u8; 4], t: f32, mode: GradientModeEnum) -> [u8; 4] {
+    let t = t.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| a as f32 + (b as f32 - a as f32) * t;
+    let mut out = [0u8; 4];
+    for c in 0..3 {
+        out[c] = match mode {
+            GradientModeEnum::LINEAR => mix(c0[c], c1[c]).round() as u8,
+            GradientModeEnum::SRGB   => {
+                let a = srgb_to_linear(c0[c] as f32 / 255.0);
+                let b = srgb_to_linear(c1[c] as f32 / 255.0);
+                (linear_to_srgb(a + (b - a) * t) * 255.0).round() as u8
+            },
+        };
+    }
+    out[3] = mix(c0[3], c1[3]).round() as u8;
+    out
+}
+
+pub fn line_gradient<'a>(v0    : LineVertex,
+                         v1    : LineVertex,
+                         imaget: &mut RgbaImage,
+                         line_method: impl Into<&'a dyn DrawBehavior>,
+                         mode  : GradientModeEnum) {
+    let clip = ClipRect::from_image(imaget);
+    gradient_pixels(v0, v1, line_method.into(), &clip, &mut |x, y, coverage, t| {
+        set_coverage(imaget, x, y, lerp_color(v0.color, v1.color, t, mode), coverage);
+    });
+}
+
+// Like `line_gradient`, but each pixel is depth tested against `depth`
+// (nearer passes) and writes its interpolated depth when drawn
+pub fn line_gradient_depth<'a>(v0    : LineVertex,
+                               v1    : LineVertex,
+                               imaget: &mut RgbaImage,
+                               depth : &mut DepthBuffer,
+                               line_method: impl Into<&'a dyn DrawBehavior>,
+                               mode  : GradientModeEnum) {
+    let clip = ClipRect::from_image(imaget)
+        .intersect(&ClipRect::new(0, 0, depth.width() as i32, depth.height() as i32));
+    gradient_pixels(v0, v1, line_method.into(), &clip, &mut |x, y, coverage, t| {
+        let z = v0.depth + (v1.depth - v0.depth) * t;
+        let stored = &mut depth[(x as u32, y as u32)].0[0];
+        if z >= *stored {return;}
+        *stored = z;
+        set_coverage(imaget, x, y, lerp_color(v0.color, v1.color, t, mode), coverage);
+    });
+}
+
+// `plot` also gets the interpolation parameter t in [0, 1] from v0 to v1
+pub(crate) fn gradient_pixels(v0  : LineVertex,
+                              v1  : LineVertex,
+                              draw_behavior: &dyn DrawBehavior,
+                              clip: &ClipRect,
+                              plot: &mut dyn FnMut(i32, i32, f32, f32)) {
+    let (p0, p1) = (v0.position, v1.position);
+    let (dx, dy) = (p1.x.value as i64 - p0.x.value as i64, p1.y.value as i64 - p0.y.value as i64);
+    let steep = dy.abs() > dx.abs();
+    let major = dx.abs().max(dy.abs()).max(1) as f64;
+    line_pixels(draw_behavior, p0, p1, clip, &mut |x, y, coverage| {
+        let t = if steep {(y as i64 - p0.y.value as i64).abs()} else {(x as i64 - p0.x.value as i64).abs()};
+        plot(x, y, coverage, (t as f64 / major).min(1.0) as f32);
+    });
+}
+
+/*------------------------------------------------------------------------------
+                    3D LINES
+------------------------------------------------------------------------------*/
+// Segments are clipped against the near and far planes in view space,
+// projected, and drawn with the depth-tested span of `line_gradient_depth`.
+// Each end is pulled toward the eye by the fraction `bias` of its view
+// distance before the test, like a polygon offset, so edges drawn over their
+// own faces win instead of z-fighting. Being relative, the offset keeps pace
+// with the depth resolution, which falls off with distance.
+#[allow(clippy::too_many_arguments)]
+pub fn line_3d<'a>(p0    : Vec3f,
+                   p1    : Vec3f,
+                   colort: [u8; 4],
+                   camera: &Camera,
+                   imaget: &mut RgbaImage,
+                   depth : &mut DepthBuffer,
+                   line_method: impl Into<&'a dyn DrawBehavior>,
+                   bias  : f32) {
+    let (width, height) = imaget.dimensions();
+    let Some((a, b)) = view_segment(p0, p1, camera) else {return;};
+    let vertex = |v: Vec3f| {
+        let s = camera.project_view(v, width, height);
+        LineVertex::new(Vec2i::new(Scalar::new(s.x.value.floor() as i32), Scalar::new(s.y.value.floor() as i32)),
+                        colort).with_depth(camera.depth(v.z.value * (1.0 - bias)))
+    };
+    line_gradient_depth(vertex(a), vertex(b), imaget, depth, line_method, GradientModeEnum::LINEAR);
+}
+
+// Screen positions and depths of what is left of the segment between the near
+// and far planes, if anything
+pub(crate) fn project_segment(p0: Vec3f, p1: Vec3f, camera: &Camera, width: u32, height: u32) -> Option<(Vec3f, Vec3f)> {
+    let (a, b) = view_segment(p0, p1, camera)?;
+    Some((camera.project_view(a, width, height), camera.project_view(b, width, height)))
+}
+
+// The same in view space
+fn view_segment(p0: Vec3f, p1: Vec3f, camera: &Camera) -> Option<(Vec3f, Vec3f)> {
+    let (mut a, mut b) = (camera.to_view(p0), camera.to_view(p1));
+    let (za, zb) = (a.z.value, b.z.value);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for &(plane, keep_above) in &[(camera.near, true), (camera.far, false)] {
+        let (da, db) = if keep_above {(za - plane, zb - plane)} else {(plane - za, plane - zb)};
+        if da < 0.0 && db < 0.0 {return None;}
+        if da < 0.0 {t0 = t0.max(da / (da - db));}
+        if db < 0.0 {t1 = t1.min(da / (da - db));}
+    }
+    if t0 > t1 {return None;}
+    let d = b - a;
+    a = a + d * Scalar::new(t0);
+    b = a + d * Scalar::new(t1 - t0);
+    Some((a, b))
+}
@@ -1,55 +1,586 @@
-use std::mem::swap;
+use std::{collections::{HashMap, HashSet},
+          fmt,
+          fs::File,
+          io::{self, BufRead, BufReader, Read},
+          mem::swap,
+          ops::Range,
+          path::Path};
 use image::RgbaImage;
 use crate::{line::*,
-            backend::{images::set, geometry::*}};
+            backend::{images::{set, blend}, geometry::*}};
 
 /*------------------------------------------------------------------------------
                     TRIANGLE METHODS (STRATEGY PATTERN)
 ------------------------------------------------------------------------------*/
-pub fn triangle (t0   : Vec2i,
-                 t1   : Vec2i,
-                 t2   : Vec2i,
-                 color: [u8; 4],
-                 image: &mut RgbaImage) {
-    let mut t0t: Vec2f = Vec2::new(Scalar::<f32>{value: t0.x.value as f32},
-                                   Scalar::<f32>{value: t0.y.value as f32});
-    let mut t1t: Vec2f = Vec2::new(Scalar::<f32>{value: t1.x.value as f32},
-                                   Scalar::<f32>{value: t1.y.value as f32});
-    let mut t2t: Vec2f = Vec2::new(Scalar::<f32>{value: t2.x.value as f32},
-                                   Scalar::<f32>{value: t2.y.value as f32});
+// Vertices are in continuous pixel coordinates snapped to 1 / 2^SUBPIXEL_BITS
+// of a pixel, so integer vertices sit on pixel corners. Pixel (x, y) is covered
+// when its center (x + 0.5, y + 0.5) lies inside the triangle. A center exactly
+// on an edge follows the top-left rule: it is covered only if the edge is a
+// top edge (horizontal with the interior below) or a left edge, so triangles
+// sharing an edge cover every pixel along it exactly once. Every method decides
+// this with exact integer arithmetic, so they all produce identical pixels.
+// Like line methods they hand pixels to `plot` and skip work outside `clip`.
+pub const SUBPIXEL_BITS: i32 = 8;
+
+pub trait FillBehavior: Send + Sync {
+    fn fill(&self,
+            v0  : Vec2f,
+            v1  : Vec2f,
+            v2  : Vec2f,
+            clip: &ClipRect,
+            plot: &mut dyn FnMut(i32, i32));
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TriangleMethodEnum {
+    SCANLINE,
+    EDGEFUNCTION,
+}
+
+pub struct Scanline;
+pub struct EdgeFunction;
+
+const SUBPIXEL: i64 = 1 << SUBPIXEL_BITS;
+const HALF    : i64 = SUBPIXEL / 2;
+// Coordinates are clamped to this many pixels so edge functions fit in an i64.
+// `subpixel_triangle_pixels` clips triangles reaching past it to a band
+// GUARD_BAND pixels around the clip rectangle first, so only a clip rectangle
+// itself that far out, or calling a FillBehavior directly, ever gets clamped.
+const COORD_LIMIT: f32 = (1 << 22) as f32;
+const GUARD_BAND : f64 = 16.0;
+
+type Fixed = (i64, i64);
+
+fn snap(v: Vec2f) -> Fixed {
+    let fixed = |c: f32| (c.clamp(-COORD_LIMIT, COORD_LIMIT) * SUBPIXEL as f32).round() as i64;
+    (fixed(v.x.value), fixed(v.y.value))
+}
+
+// Positive when `p` is on the inner side of a -> b for a triangle wound
+// like (a, b, c) with a positive area
+fn edge(a: Fixed, b: Fixed, p: Fixed) -> i64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+// With that winding, top edges run rightward and left edges run upward
+fn is_top_left(a: Fixed, b: Fixed) -> bool {
+    (a.1 == b.1 && b.0 > a.0) || b.1 < a.1
+}
+
+fn floor_div(a: i64, b: i64) -> i64 {
+    a.div_euclid(b)
+}
+
+fn ceil_div(a: i64, b: i64) -> i64 {
+    -(-a).div_euclid(b)
+}
+
+// Where a -> b crosses the row `y`, as a fraction (numerator, denominator > 0)
+fn crossing(a: Fixed, b: Fixed, y: i64) -> (i64, i64) {
+    (a.0 * (b.1 - a.1) + (b.0 - a.0) * (y - a.1), b.1 - a.1)
+}
+
+// First pixel whose center is at or past the fraction `c`
+fn first_center(c: (i64, i64)) -> i64 {
+    ceil_div(c.0 - HALF * c.1, SUBPIXEL * c.1)
+}
 
-    if (t0t.y.value == t1t.y.value) && (t0t.y.value == t2t.y.value) {return;}
-    if t0t.y.value > t1t.y.value {swap(&mut t0t, &mut t1t);}
-    if t0t.y.value > t2t.y.value {swap(&mut t0t, &mut t2t);}
-    if t1t.y.value > t2t.y.value {swap(&mut t1t, &mut t2t);}
+// Sweeps rows from top to bottom between the long edge t0 -> t2 and whichever
+// short edge spans the row. Span starts are inclusive and ends exclusive, and
+// so are the top and bottom rows, which is the top-left rule row by row.
+impl FillBehavior for Scanline {
+    fn fill(&self, v0: Vec2f, v1: Vec2f, v2: Vec2f, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+        let (mut t0, mut t1, mut t2) = (snap(v0), snap(v1), snap(v2));
+        if edge(t0, t1, t2) == 0 {return;}
+        if t0.1 > t1.1 {swap(&mut t0, &mut t1);}
+        if t0.1 > t2.1 {swap(&mut t0, &mut t2);}
+        if t1.1 > t2.1 {swap(&mut t1, &mut t2);}
+        // The long edge is on the left when t1 bulges to the right of it
+        let long_left = edge(t0, t2, t1) < 0;
 
-    let total_height: i32 = (t2t.y.value - t0t.y.value) as i32;
-    for k in 0..total_height {
-        let i = k as f32;
-        let second_half: bool  = i > (t1t.y.value - t0t.y.value) || t1t.y.value == t0t.y.value;
-        let segment_height: f32 = if second_half {(t2t.y.value - t1t.y.value)}
-                                  else {(t1t.y.value - t0t.y.value)};
+        let y0 = first_center((t0.1, 1)).max(clip.y0 as i64);
+        let y1 = first_center((t2.1, 1)).min(clip.y1 as i64);
+        for y in y0..y1 {
+            let sy = y * SUBPIXEL + HALF;
+            let second_half = sy >= t1.1;
+            let a = crossing(t0, t2, sy);
+            let b = if second_half {crossing(t1, t2, sy)} else {crossing(t0, t1, sy)};
+            let (left, right) = if long_left {(a, b)} else {(b, a)};
 
-        let alpha: f32 = i / total_height as f32;
-        let beta : f32 = i - (if second_half {t1t.y.value - t0t.y.value} else {0.0}) / segment_height;
+            let x0 = first_center(left).max(clip.x0 as i64);
+            let x1 = first_center(right).min(clip.x1 as i64);
+            for x in x0..x1 {
+                plot(x as i32, y as i32);
+            }
+        }
+    }
+}
 
-        let mut A: Vec2f = t0t + (t2t - t0t) * Scalar::<f32>{value: alpha};
-        let mut B: Vec2f = if second_half {t1t + (t2t - t1t) * Scalar::<f32>{value: beta}}
-                       else {t0t + (t1t - t0t) * Scalar::<f32>{value: beta}};
+// Tests the center of every pixel in the bounding box against the three edge
+// functions, stepping them incrementally along each row
+impl FillBehavior for EdgeFunction {
+    fn fill(&self, v0: Vec2f, v1: Vec2f, v2: Vec2f, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+        let (t0, mut t1, mut t2) = (snap(v0), snap(v1), snap(v2));
+        let area = edge(t0, t1, t2);
+        if area == 0 {return;}
+        if area < 0 {swap(&mut t1, &mut t2);}
 
-        if A.x.value > B.x.value {swap(&mut A, &mut B)};
-        for j in A.x.value as i32..B.y.value as i32 {
-            //println!("{:?}", j)
-            set(image, j, (t0t.y.value + i) as i32, color);
+        let x0 = first_center((t0.0.min(t1.0).min(t2.0), 1)).max(clip.x0 as i64);
+        let y0 = first_center((t0.1.min(t1.1).min(t2.1), 1)).max(clip.y0 as i64);
+        let x1 = floor_div(t0.0.max(t1.0).max(t2.0) - HALF, SUBPIXEL).min(clip.x1 as i64 - 1);
+        let y1 = floor_div(t0.1.max(t1.1).max(t2.1) - HALF, SUBPIXEL).min(clip.y1 as i64 - 1);
+        let edges = [(t0, t1), (t1, t2), (t2, t0)];
+        // Centers on an edge that is neither top nor left need a positive value
+        let bias = edges.map(|(a, b)| if is_top_left(a, b) {0} else {-1});
+        let step = edges.map(|(a, b)| -(b.1 - a.1) * SUBPIXEL);
+        for y in y0..=y1 {
+            let start = (x0 * SUBPIXEL + HALF, y * SUBPIXEL + HALF);
+            let mut w = [0, 1, 2].map(|i| edge(edges[i].0, edges[i].1, start) + bias[i]);
+            for x in x0..=x1 {
+                if w.iter().all(|&e| e >= 0) {plot(x as i32, y as i32);}
+                for (e, s) in w.iter_mut().zip(step) {*e += s;}
+            }
         }
     }
 }
-/*
+
+fn fill_behavior(triangle_method: TriangleMethodEnum) -> &'static dyn FillBehavior {
+    match triangle_method {
+        TriangleMethodEnum::SCANLINE     => &Scanline,
+        TriangleMethodEnum::EDGEFUNCTION => &EdgeFunction,
+    }
+}
+
+fn corner(v: Vec2i) -> Vec2f {
+    Vec2f::new(Scalar::new(v.x.value as f32), Scalar::new(v.y.value as f32))
+}
+
 pub fn triangle (t0   : Vec2i,
                  t1   : Vec2i,
                  t2   : Vec2i,
                  color: [u8; 4],
-                 image: &mut RgbaImage) {
-    // Sort the vectors
-    if t1.y - t0.y > 0
-}*/
\ No newline at end of file
+                 image: &mut RgbaImage,
+                 triangle_method: TriangleMethodEnum) {
+    triangle_subpixel(corner(t0), corner(t1), corner(t2), color, image, triangle_method);
+}
+
+pub fn triangle_subpixel (v0   : Vec2f,
+                          v1   : Vec2f,
+                          v2   : Vec2f,
+                          color: [u8; 4],
+                          image: &mut RgbaImage,
+                          triangle_method: TriangleMethodEnum) {
+    let clip = ClipRect::from_image(image);
+    subpixel_triangle_pixels(v0, v1, v2, triangle_method, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+// Runs the chosen filler without an image, handing every pixel inside `clip` to `plot`
+pub(crate) fn triangle_pixels (t0  : Vec2i,
+                               t1  : Vec2i,
+                               t2  : Vec2i,
+                               triangle_method: TriangleMethodEnum,
+                               clip: &ClipRect,
+                               plot: &mut dyn FnMut(i32, i32)) {
+    subpixel_triangle_pixels(corner(t0), corner(t1), corner(t2), triangle_method, clip, plot);
+}
+
+pub(crate) fn subpixel_triangle_pixels (v0  : Vec2f,
+                                        v1  : Vec2f,
+                                        v2  : Vec2f,
+                                        triangle_method: TriangleMethodEnum,
+                                        clip: &ClipRect,
+                                        plot: &mut dyn FnMut(i32, i32)) {
+    let behavior = fill_behavior(triangle_method);
+    let beyond = |v: &Vec2f| v.x.value.abs() > COORD_LIMIT || v.y.value.abs() > COORD_LIMIT;
+    if ![v0, v1, v2].iter().any(beyond) {
+        behavior.fill(v0, v1, v2, clip, plot);
+        return;
+    }
+    // The clipped polygon is convex, so a fan from its first vertex covers it
+    // exactly once; pixel centers inside `clip` see the original edges
+    let polygon = guard_band_clip([v0, v1, v2], clip);
+    for i in 1..polygon.len().saturating_sub(1) {
+        behavior.fill(polygon[0], polygon[i], polygon[i + 1], clip, plot);
+    }
+}
+
+// Sutherland-Hodgman clip of a triangle to `clip` grown by GUARD_BAND, in f64
+// so the new vertices stay on the original edges
+fn guard_band_clip(triangle: [Vec2f; 3], clip: &ClipRect) -> Vec<Vec2f> {
+    if triangle.iter().any(|v| !v.x.value.is_finite() || !v.y.value.is_finite()) {return Vec::new();}
+    let mut polygon: Vec<[f64; 2]> = triangle.iter().map(|v| [v.x.value as f64, v.y.value as f64]).collect();
+    // (axis, bound, +1 to keep coordinates above the bound or -1 below it)
+    let sides = [(0, clip.x0 as f64 - GUARD_BAND,  1.0), (0, clip.x1 as f64 + GUARD_BAND, -1.0),
+                 (1, clip.y0 as f64 - GUARD_BAND,  1.0), (1, clip.y1 as f64 + GUARD_BAND, -1.0)];
+    for &(axis, bound, sign) in &sides {
+        let inside = |p: [f64; 2]| sign * (p[axis] - bound) >= 0.0;
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            if inside(a) {clipped.push(a);}
+            if inside(a) != inside(b) {
+                let t = (bound - a[axis]) / (b[axis] - a[axis]);
+                let mut p = [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])];
+                p[axis] = bound;
+                clipped.push(p);
+            }
+        }
+        polygon = clipped;
+    }
+    polygon.iter().map(|p| Vec2f::new(Scalar::new(p[0] as f32), Scalar::new(p[1] as f32))).collect()
+}
+
+/*------------------------------------------------------------------------------
+                    ANTI-ALIASED TRIANGLES
+------------------------------------------------------------------------------*/
+// Vertices are in continuous pixel coordinates: pixel (x, y) covers the square
+// [x, x + 1) x [y, y + 1). Each pixel is tested at COVERAGE_GRID^2 sub-pixel
+// samples packed into a bit mask, so shapes built from several triangles can
+// union their masks and blend each pixel once, without seams or double blending.
+pub const COVERAGE_GRID: i32 = 4;
+
+pub fn triangle_aa (v0   : Vec2f,
+                    v1   : Vec2f,
+                    v2   : Vec2f,
+                    color: [u8; 4],
+                    image: &mut RgbaImage) {
+    let mut mask = CoverageMask::new(&[[v0, v1, v2]], ClipRect::from_image(image));
+    mask.triangle(v0, v1, v2);
+    mask.pixels(&mut |x, y, coverage| {blend(image, x, y, color, coverage);});
+}
+
+// Hands `plot` the sample mask of every pixel inside `clip` the triangle touches
+pub(crate) fn triangle_coverage (v0  : Vec2f,
+                                 v1  : Vec2f,
+                                 v2  : Vec2f,
+                                 clip: &ClipRect,
+                                 plot: &mut dyn FnMut(i32, i32, u16)) {
+    let edge = |a: Vec2f, b: Vec2f, x: f32, y: f32| {
+        (b.x.value - a.x.value) * (y - a.y.value) - (b.y.value - a.y.value) * (x - a.x.value)
+    };
+    let area = edge(v0, v1, v2.x.value, v2.y.value);
+    if area == 0.0 || !area.is_finite() {return;}
+    let (v1, v2) = if area < 0.0 {(v2, v1)} else {(v1, v2)};
+
+    let xs = [v0.x.value, v1.x.value, v2.x.value];
+    let ys = [v0.y.value, v1.y.value, v2.y.value];
+    let bounds = ClipRect::new(xs.iter().cloned().fold(f32::INFINITY, f32::min).floor() as i32,
+                               ys.iter().cloned().fold(f32::INFINITY, f32::min).floor() as i32,
+                               xs.iter().cloned().fold(f32::NEG_INFINITY, f32::max).floor() as i32 + 1,
+                               ys.iter().cloned().fold(f32::NEG_INFINITY, f32::max).floor() as i32 + 1)
+        .intersect(clip);
+    if bounds.is_empty() {return;}
+
+    let step = 1.0 / COVERAGE_GRID as f32;
+    for y in bounds.y0..bounds.y1 {
+        for x in bounds.x0..bounds.x1 {
+            let mut mask: u16 = 0;
+            for j in 0..COVERAGE_GRID {
+                let sy = y as f32 + (j as f32 + 0.5) * step;
+                for i in 0..COVERAGE_GRID {
+                    let sx = x as f32 + (i as f32 + 0.5) * step;
+                    if edge(v0, v1, sx, sy) >= 0.0 &&
+                       edge(v1, v2, sx, sy) >= 0.0 &&
+                       edge(v2, v0, sx, sy) >= 0.0 {
+                        mask |= 1 << (j * COVERAGE_GRID + i);
+                    }
+                }
+            }
+            if mask != 0 {plot(x, y, mask);}
+        }
+    }
+}
+
+// Union of triangle sample masks over the pixels their bounds share with `clip`
+pub(crate) struct CoverageMask {
+    bounds: ClipRect,
+    masks : Vec<u16>,
+}
+
+impl CoverageMask {
+    pub(crate) fn new(triangles: &[[Vec2f; 3]], clip: ClipRect) -> CoverageMask {
+        let (mut x0, mut y0) = (f32::INFINITY, f32::INFINITY);
+        let (mut x1, mut y1) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for v in triangles.iter().flatten() {
+            x0 = x0.min(v.x.value);
+            y0 = y0.min(v.y.value);
+            x1 = x1.max(v.x.value);
+            y1 = y1.max(v.y.value);
+        }
+        let bounds = if triangles.is_empty() {ClipRect::new(0, 0, 0, 0)}
+                     else {ClipRect::new(x0.floor() as i32, y0.floor() as i32,
+                                         x1.floor() as i32 + 1, y1.floor() as i32 + 1).intersect(&clip)};
+        let size = if bounds.is_empty() {0}
+                   else {((bounds.x1 - bounds.x0) * (bounds.y1 - bounds.y0)) as usize};
+        CoverageMask{bounds, masks: vec![0; size]}
+    }
+
+    pub(crate) fn triangle(&mut self, v0: Vec2f, v1: Vec2f, v2: Vec2f) {
+        if self.masks.is_empty() {return;}
+        let bounds = self.bounds;
+        let width  = bounds.x1 - bounds.x0;
+        let masks  = &mut self.masks;
+        triangle_coverage(v0, v1, v2, &bounds, &mut |x, y, mask| {
+            masks[((y - bounds.y0) * width + (x - bounds.x0)) as usize] |= mask;
+        });
+    }
+
+    // Covered pixels with their coverage fraction, row by row
+    pub(crate) fn pixels(&self, plot: &mut dyn FnMut(i32, i32, f32)) {
+        if self.masks.is_empty() {return;}
+        let width   = self.bounds.x1 - self.bounds.x0;
+        let samples = (COVERAGE_GRID * COVERAGE_GRID) as f32;
+        for (i, mask) in self.masks.iter().enumerate() {
+            if *mask == 0 {continue;}
+            let (x, y) = (self.bounds.x0 + i as i32 % width, self.bounds.y0 + i as i32 / width);
+            plot(x, y, mask.count_ones() as f32 / samples);
+        }
+    }
+}
+
+/*------------------------------------------------------------------------------
+                    MESHES
+------------------------------------------------------------------------------*/
+// Indexed triangle mesh. Faces are wound counter-clockwise when seen from the
+// side they face. `normals` and `texcoords` hold one entry per position and may
+// be left empty; renderers then fall back to face normals. `groups` names runs
+// of triangles and may be empty too.
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mesh {
+    pub positions: Vec<Vec3f>,
+    pub normals  : Vec<Vec3f>,
+    pub texcoords: Vec<Vec2f>,
+    pub triangles: Vec<[usize; 3]>,
+    pub groups   : Vec<MeshGroup>,
+}
+
+// Triangles of a mesh named by an object and a group, like OBJ `o` and `g`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshGroup {
+    pub object   : String,
+    pub name     : String,
+    pub triangles: Range<usize>,
+}
+
+impl Mesh {
+    pub fn new(positions: Vec<Vec3f>, triangles: Vec<[usize; 3]>) -> Mesh {
+        Mesh{positions, triangles, ..Mesh::default()}
+    }
+
+    // Unnormalized normal of triangle `t`, twice its area long
+    fn face_area_normal(&self, t: usize) -> Vec3f {
+        let [a, b, c] = self.triangles[t].map(|i| self.positions[i]);
+        (b - a).cross(&(c - a))
+    }
+
+    // Unit normal of triangle `t`, or zero when it has no area
+    pub fn face_normal(&self, t: usize) -> Vec3f {
+        let n = self.face_area_normal(t);
+        if n.norm() > 0.0 {n.normalize()} else {n}
+    }
+
+    // Every edge once, as index pairs in order of first use. Edges are matched
+    // by vertex position, so seams where vertices are split count only once.
+    pub fn edges(&self) -> Vec<[usize; 2]> {
+        let key = |i: usize| {
+            let p = self.positions[i];
+            // Adding zero folds -0.0 into 0.0
+            [p.x.value, p.y.value, p.z.value].map(|c| (c + 0.0).to_bits())
+        };
+        let mut seen  = HashSet::new();
+        let mut edges = Vec::new();
+        for t in &self.triangles {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                let (ka, kb) = (key(a), key(b));
+                if ka == kb {continue;}
+                if seen.insert(if ka < kb {(ka, kb)} else {(kb, ka)}) {edges.push([a, b]);}
+            }
+        }
+        edges
+    }
+
+    // Sets every vertex normal to the area-weighted average of the faces around it
+    pub fn compute_normals(&mut self) {
+        let zero = Vec3f::new(Scalar::new(0.0), Scalar::new(0.0), Scalar::new(0.0));
+        let mut normals = vec![zero; self.positions.len()];
+        for t in 0..self.triangles.len() {
+            let n = self.face_area_normal(t);
+            for &i in &self.triangles[t] {
+                normals[i] = normals[i] + n;
+            }
+        }
+        self.normals = normals.into_iter().map(|n| if n.norm() > 0.0 {n.normalize()} else {n}).collect();
+    }
+}
+
+/*------------------------------------------------------------------------------
+                    WAVEFRONT OBJ
+------------------------------------------------------------------------------*/
+// Reads `v`, `vt`, `vn` and `f` statements, with 1-based or negative (relative
+// to the end) indices. Polygons are split into triangle fans, `o` and `g` start
+// new mesh groups, and other statements such as materials are ignored.
+//
+// Each distinct combination of position, texture coordinate and normal becomes
+// one mesh vertex. Corners without a normal get one averaged over the faces
+// that share them: faces in the same smoothing group (`s 1`, `s 2`, ...) share
+// vertices and blend, and faces with smoothing off keep their own. A file with
+// neither normals nor smoothing groups leaves `normals` empty.
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    Parse{line: usize, message: String},
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(e)                => write!(f, "obj i/o error: {}", e),
+            ObjError::Parse{line, message} => write!(f, "malformed obj data at line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<io::Error> for ObjError {
+    fn from(e: io::Error) -> ObjError {
+        ObjError::Io(e)
+    }
+}
+
+// Position, texture coordinate and normal indices of a face corner
+type Corner = (usize, Option<usize>, Option<usize>);
+
+struct ObjTriangle {
+    corners  : [Corner; 3],
+    // Smoothing group, 0 when off
+    smoothing: u32,
+    polygon  : usize,
+}
+
+fn floats(args: &[&str], min: usize, line: usize) -> Result<Vec<f32>, ObjError> {
+    if args.len() < min {
+        return Err(ObjError::Parse{line, message: format!("expected at least {} numbers", min)});
+    }
+    args.iter().map(|a| a.parse::<f32>().map_err(|_| ObjError::Parse{line, message: format!("invalid number `{}`", a)}))
+        .collect()
+}
+
+// Resolves a 1-based or negative index into `count` elements read so far
+fn resolve(index: &str, count: usize, kind: &str, line: usize) -> Result<usize, ObjError> {
+    let i: i64 = index.parse().map_err(|_| ObjError::Parse{line, message: format!("invalid {} index `{}`", kind, index)})?;
+    let resolved = if i > 0 {i - 1} else {count as i64 + i};
+    if i == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(ObjError::Parse{line, message: format!("{} index {} out of range, {} defined", kind, i, count)});
+    }
+    Ok(resolved as usize)
+}
+
+fn face_corner(token: &str, counts: (usize, usize, usize), line: usize) -> Result<Corner, ObjError> {
+    let parts: Vec<&str> = token.split('/').collect();
+    if parts.len() > 3 || parts[0].is_empty() {
+        return Err(ObjError::Parse{line, message: format!("invalid face corner `{}`", token)});
+    }
+    let optional = |i: usize, count: usize, kind: &str| match parts.get(i) {
+        Some(p) if !p.is_empty() => resolve(p, count, kind, line).map(Some),
+        _ => Ok(None),
+    };
+    Ok((resolve(parts[0], counts.0, "vertex", line)?,
+        optional(1, counts.1, "texture coordinate")?,
+        optional(2, counts.2, "normal")?))
+}
+
+pub fn read_obj<R: Read>(r: &mut R) -> Result<Mesh, ObjError> {
+    let vec3 = |v: &[f32]| Vec3f::new(Scalar::new(v[0]), Scalar::new(v[1]), Scalar::new(v[2]));
+    let (mut positions, mut texcoords, mut normals) = (Vec::new(), Vec::new(), Vec::new());
+    let mut triangles: Vec<ObjTriangle> = Vec::new();
+    let mut groups: Vec<MeshGroup> = Vec::new();
+    let (mut object, mut group, mut smoothing, mut polygons) = (String::new(), String::from("default"), 0, 0);
+
+    for (index, text) in BufReader::new(r).lines().enumerate() {
+        let (line, text) = (index + 1, text?);
+        let args: Vec<&str> = text.split('#').next().unwrap_or("").split_whitespace().collect();
+        let Some((&keyword, args)) = args.split_first() else {continue;};
+        match keyword {
+            "v"  => positions.push(vec3(&floats(args, 3, line)?)),
+            "vt" => {
+                let uv = floats(args, 1, line)?;
+                texcoords.push(Vec2f::new(Scalar::new(uv[0]), Scalar::new(uv.get(1).copied().unwrap_or(0.0))));
+            },
+            "vn" => normals.push(vec3(&floats(args, 3, line)?)),
+            "f"  => {
+                if args.len() < 3 {
+                    return Err(ObjError::Parse{line, message: format!("face needs at least 3 corners, got {}", args.len())});
+                }
+                let counts  = (positions.len(), texcoords.len(), normals.len());
+                let corners = args.iter().map(|a| face_corner(a, counts, line)).collect::<Result<Vec<_>, _>>()?;
+                if groups.last().is_none_or(|g| g.object != object || g.name != group) {
+                    groups.push(MeshGroup{object: object.clone(), name: group.clone(), triangles: triangles.len()..triangles.len()});
+                }
+                for i in 1..corners.len() - 1 {
+                    triangles.push(ObjTriangle{corners: [corners[0], corners[i], corners[i + 1]], smoothing, polygon: polygons});
+                }
+                polygons += 1;
+                if let Some(g) = groups.last_mut() {g.triangles.end = triangles.len();}
+            },
+            "o"  => object = args.join(" "),
+            "g"  => group = if args.is_empty() {String::from("default")} else {args.join(" ")},
+            "s"  => smoothing = match args.first() {
+                None | Some(&"off") => 0,
+                Some(n) => n.parse().map_err(|_| ObjError::Parse{line, message: format!("invalid smoothing group `{}`", n)})?,
+            },
+            _    => {},
+        }
+    }
+
+    // Corners without a normal are split by smoothing group, or per polygon
+    // when smoothing is off, so averaging normals over shared vertices smooths
+    // exactly within each group
+    let any_normals   = triangles.iter().any(|t| t.corners.iter().any(|c| c.2.is_some()));
+    let any_smoothing = triangles.iter().any(|t| t.smoothing != 0);
+    let with_normals  = any_normals || any_smoothing;
+    let with_uvs      = triangles.iter().any(|t| t.corners.iter().any(|c| c.1.is_some()));
+
+    let mut mesh = Mesh{groups, ..Mesh::default()};
+    let mut missing = Vec::new();
+    let mut vertices: HashMap<(Corner, Option<(u32, usize)>), usize> = HashMap::new();
+    for t in &triangles {
+        let indices = t.corners.map(|c| {
+            let sharing = if !with_normals || c.2.is_some() {None}
+                          else if t.smoothing != 0 {Some((t.smoothing, 0))}
+                          else {Some((0, t.polygon))};
+            *vertices.entry((c, sharing)).or_insert_with(|| {
+                mesh.positions.push(positions[c.0]);
+                if with_uvs {
+                    mesh.texcoords.push(c.1.map_or(Vec2f::new(Scalar::new(0.0), Scalar::new(0.0)), |i| texcoords[i]));
+                }
+                if with_normals {
+                    mesh.normals.push(c.2.map_or(Vec3f::new(Scalar::new(0.0), Scalar::new(0.0), Scalar::new(0.0)), |i| normals[i]));
+                    missing.push(c.2.is_none());
+                }
+                mesh.positions.len() - 1
+            })
+        });
+        mesh.triangles.push(indices);
+    }
+
+    if missing.contains(&true) {
+        for t in 0..mesh.triangles.len() {
+            let n = mesh.face_area_normal(t);
+            for &i in &mesh.triangles[t] {
+                if missing[i] {mesh.normals[i] = mesh.normals[i] + n;}
+            }
+        }
+        for (n, _) in mesh.normals.iter_mut().zip(&missing).filter(|(_, &m)| m) {
+            if n.norm() > 0.0 {*n = n.normalize();}
+        }
+    }
+    Ok(mesh)
+}
+
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Mesh, ObjError> {
+    read_obj(&mut File::open(path)?)
+}
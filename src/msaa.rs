@@ -0,0 +1,136 @@
+use image::{RgbaImage, Rgba, Luma};
+use crate::backend::{images::DepthBuffer, geometry::*};
+
+/*------------------------------------------------------------------------------
+                    MULTISAMPLE ANTI-ALIASING (MSAA)
+------------------------------------------------------------------------------*/
+// Every pixel stores `n` color and depth samples at fixed sub-pixel
+// positions. Coverage and depth are evaluated per sample, but a triangle is
+// shaded only once per pixel (at the pixel center) and that color is written
+// to every covered sample that passes the depth test. Smaller depth is nearer.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SampleCountEnum {
+    MSAA2,
+    MSAA4,
+    MSAA8,
+}
+
+impl SampleCountEnum {
+    pub fn count(&self) -> usize {
+        match self {
+            SampleCountEnum::MSAA2 => 2,
+            SampleCountEnum::MSAA4 => 4,
+            SampleCountEnum::MSAA8 => 8,
+        }
+    }
+
+    // Standard (Direct3D) sample positions in 1/16 pixel offsets from center
+    pub fn pattern(&self) -> &'static [(i8, i8)] {
+        match self {
+            SampleCountEnum::MSAA2 => &[(4, 4), (-4, -4)],
+            SampleCountEnum::MSAA4 => &[(-2, -6), (6, -2), (-6, 2), (2, 6)],
+            SampleCountEnum::MSAA8 => &[(1, -3), (-1, 3), (5, 1), (-3, -5),
+                                        (-5, 5), (-7, -1), (3, 7), (7, -7)],
+        }
+    }
+}
+
+pub struct MsaaTarget {
+    width  : u32,
+    height : u32,
+    samples: SampleCountEnum,
+    colors : Vec<[u8; 4]>,
+    depth  : Vec<f32>,
+}
+
+fn edge(a: (f32, f32), b: (f32, f32), p: (f32, f32)) -> f32 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+impl MsaaTarget {
+    pub fn new(width: u32, height: u32, samples: SampleCountEnum) -> MsaaTarget {
+        let len = (width * height) as usize * samples.count();
+        MsaaTarget{width,
+                   height,
+                   samples,
+                   colors: vec![[0, 0, 0, 0]; len],
+                   depth : vec![f32::INFINITY; len]}
+    }
+
+    pub fn clear(&mut self, color: [u8; 4], depth: f32) {
+        self.colors.iter_mut().for_each(|c| *c = color);
+        self.depth.iter_mut().for_each(|d| *d = depth);
+    }
+
+    // `shade` receives the pixel and the barycentric weights of its center
+    pub fn triangle_shaded(&mut self,
+                           v0   : Vec3f,
+                           v1   : Vec3f,
+                           v2   : Vec3f,
+                           shade: &mut dyn FnMut(u32, u32, [f32; 3]) -> [u8; 4]) {
+        let p = [(v0.x.value, v0.y.value), (v1.x.value, v1.y.value), (v2.x.value, v2.y.value)];
+        let z = [v0.z.value, v1.z.value, v2.z.value];
+        let area = edge(p[0], p[1], p[2]);
+        if area == 0.0 {return;}
+
+        let min_x = p.iter().map(|v| v.0).fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+        let min_y = p.iter().map(|v| v.1).fold(f32::MAX, f32::min).floor().max(0.0) as u32;
+        let max_x = p.iter().map(|v| v.0).fold(f32::MIN, f32::max).ceil().min(self.width as f32) as u32;
+        let max_y = p.iter().map(|v| v.1).fold(f32::MIN, f32::max).ceil().min(self.height as f32) as u32;
+
+        // Normalised barycentrics, valid for either winding
+        let bary = |q: (f32, f32)| [edge(p[1], p[2], q) / area,
+                                    edge(p[2], p[0], q) / area,
+                                    edge(p[0], p[1], q) / area];
+        let n = self.samples.count();
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let base = (y * self.width + x) as usize * n;
+                let mut color = None;
+                for (s, &(sx, sy)) in self.samples.pattern().iter().enumerate() {
+                    let q = (x as f32 + 0.5 + sx as f32 / 16.0, y as f32 + 0.5 + sy as f32 / 16.0);
+                    let b = bary(q);
+                    if b[0] < 0.0 || b[1] < 0.0 || b[2] < 0.0 {continue;}
+                    let depth = b[0] * z[0] + b[1] * z[1] + b[2] * z[2];
+                    if depth >= self.depth[base + s] {continue;}
+
+                    let c = *color.get_or_insert_with(|| shade(x, y, bary((x as f32 + 0.5, y as f32 + 0.5))));
+                    self.colors[base + s] = c;
+                    self.depth[base + s]  = depth;
+                }
+            }
+        }
+    }
+
+    pub fn triangle(&mut self,
+                    v0   : Vec3f,
+                    v1   : Vec3f,
+                    v2   : Vec3f,
+                    color: [u8; 4]) {
+        self.triangle_shaded(v0, v1, v2, &mut |_, _, _| color);
+    }
+
+    // Box resolve: the average of each pixel's samples
+    pub fn resolve(&self) -> RgbaImage {
+        let n = self.samples.count();
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let base = (y * self.width + x) as usize * n;
+            let mut sum = [0u32; 4];
+            for c in &self.colors[base..base + n] {
+                for i in 0..4 {sum[i] += c[i] as u32;}
+            }
+            Rgba(sum.map(|v| ((v + n as u32 / 2) / n as u32) as u8))
+        })
+    }
+
+    // Nearest sample depth per pixel
+    pub fn resolve_depth(&self) -> DepthBuffer {
+        let n = self.samples.count();
+        DepthBuffer::from_fn(self.width, self.height, |x, y| {
+            let base = (y * self.width + x) as usize * n;
+            Luma([self.depth[base..base + n].iter().cloned().fold(f32::INFINITY, f32::min)])
+        })
+    }
+}
@@ -0,0 +1,372 @@
+use std::{collections::HashMap, f32::consts::PI};
+use image::RgbaImage;
+use crate::{line::{ClipRect, DrawBehavior, LinePatternEnum, polyline_pixels},
+            model::{CoverageMask, COVERAGE_GRID},
+            stroke::Stroke,
+            backend::{images::{blend, set_coverage}, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    PATHS
+------------------------------------------------------------------------------*/
+// Points are in continuous pixel coordinates, like strokes. Curves and arcs
+// are flattened into polylines before drawing; every way of rendering a path
+// hands each pixel to the image once, so translucent colors stay even where
+// segments and subpaths meet.
+
+// Flattened curves stay within this many pixels of the exact curve
+pub const PATH_TOLERANCE: f32 = 0.25;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PathCommandEnum {
+    MOVETO(Vec2f),
+    LINETO(Vec2f),
+    QUADTO(Vec2f, Vec2f),
+    CUBICTO(Vec2f, Vec2f, Vec2f),
+    // SVG elliptical arc: radii, x-axis rotation in radians, large-arc and
+    // sweep flags, end point
+    ARCTO{radii: Vec2f, rotation: f32, large_arc: bool, sweep: bool, to: Vec2f},
+    CLOSE,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FillRuleEnum {
+    NONZERO,
+    EVENODD,
+}
+
+// One flattened, connected run of points
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpath {
+    pub points: Vec<Vec2f>,
+    pub closed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    commands: Vec<PathCommandEnum>,
+}
+
+fn vec2(x: f32, y: f32) -> Vec2f {
+    Vec2f::new(Scalar::new(x), Scalar::new(y))
+}
+
+fn lerp(a: Vec2f, b: Vec2f, t: f32) -> Vec2f {
+    a + (b - a) * Scalar::new(t)
+}
+
+fn length(v: Vec2f) -> f32 {
+    v.x.value.hypot(v.y.value)
+}
+
+// Segments needed for a curve whose second difference is `dd` long
+fn curve_segments(dd: f32, tolerance: f32) -> usize {
+    ((dd / tolerance).sqrt().ceil() as usize).clamp(1, 1024)
+}
+
+impl Path {
+    pub fn new() -> Path {
+        Path::default()
+    }
+
+    pub fn commands(&self) -> &[PathCommandEnum] {
+        &self.commands
+    }
+
+    pub fn move_to(&mut self, p: Vec2f) -> &mut Path {
+        self.commands.push(PathCommandEnum::MOVETO(p));
+        self
+    }
+
+    pub fn line_to(&mut self, p: Vec2f) -> &mut Path {
+        self.commands.push(PathCommandEnum::LINETO(p));
+        self
+    }
+
+    pub fn quad_to(&mut self, control: Vec2f, p: Vec2f) -> &mut Path {
+        self.commands.push(PathCommandEnum::QUADTO(control, p));
+        self
+    }
+
+    pub fn cubic_to(&mut self, c0: Vec2f, c1: Vec2f, p: Vec2f) -> &mut Path {
+        self.commands.push(PathCommandEnum::CUBICTO(c0, c1, p));
+        self
+    }
+
+    pub fn arc_to(&mut self, radii: Vec2f, rotation: f32, large_arc: bool, sweep: bool, p: Vec2f) -> &mut Path {
+        self.commands.push(PathCommandEnum::ARCTO{radii, rotation, large_arc, sweep, to: p});
+        self
+    }
+
+    pub fn close(&mut self) -> &mut Path {
+        self.commands.push(PathCommandEnum::CLOSE);
+        self
+    }
+
+    // Drawing without a current point starts a subpath at the target, as in
+    // the HTML canvas. After `close` the next command continues from the
+    // subpath's first point.
+    pub fn flatten(&self, tolerance: f32) -> Vec<Subpath> {
+        let tolerance = if tolerance > 0.0 {tolerance} else {PATH_TOLERANCE};
+        let mut out: Vec<Subpath> = Vec::new();
+        let mut current: Option<Subpath> = None;
+        let mut start = vec2(0.0, 0.0);
+
+        for command in &self.commands {
+            if let PathCommandEnum::MOVETO(p) = command {
+                out.extend(current.take());
+                start = *p;
+                current = Some(Subpath{points: vec![*p], closed: false});
+                continue;
+            }
+            if *command == PathCommandEnum::CLOSE {
+                if let Some(mut sub) = current.take() {
+                    sub.closed = true;
+                    out.push(sub);
+                }
+                continue;
+            }
+
+            let target = match command {
+                PathCommandEnum::LINETO(p) | PathCommandEnum::QUADTO(_, p) |
+                PathCommandEnum::CUBICTO(_, _, p) | PathCommandEnum::ARCTO{to: p, ..} => *p,
+                _ => unreachable!(),
+            };
+            let sub = current.get_or_insert_with(|| {
+                let from = if out.last().is_some_and(|s| s.closed) {start} else {target};
+                start = from;
+                Subpath{points: vec![from], closed: false}
+            });
+            let p0 = *sub.points.last().unwrap();
+            let points = &mut sub.points;
+            match *command {
+                PathCommandEnum::LINETO(p) => points.push(p),
+                PathCommandEnum::QUADTO(c, p) => {
+                    let n = curve_segments(length(p0 - c * Scalar::new(2.0) + p) / 4.0, tolerance);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        points.push(lerp(lerp(p0, c, t), lerp(c, p, t), t));
+                    }
+                },
+                PathCommandEnum::CUBICTO(c0, c1, p) => {
+                    let dd = length(p0 - c0 * Scalar::new(2.0) + c1)
+                        .max(length(c0 - c1 * Scalar::new(2.0) + p));
+                    let n = curve_segments(dd * 0.75, tolerance);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let (a, b, c) = (lerp(p0, c0, t), lerp(c0, c1, t), lerp(c1, p, t));
+                        points.push(lerp(lerp(a, b, t), lerp(b, c, t), t));
+                    }
+                },
+                PathCommandEnum::ARCTO{radii, rotation, large_arc, sweep, to} => {
+                    arc_points(p0, radii, rotation, large_arc, sweep, to, tolerance, points);
+                },
+                _ => unreachable!(),
+            }
+        }
+        out.extend(current);
+        out
+    }
+
+    // Every subpath drawn as a one-pixel polyline; points land in the pixel
+    // that contains them
+    pub fn stroke<'a>(&self,
+                      color: [u8; 4],
+                      image: &mut RgbaImage,
+                      line_method: impl Into<&'a dyn DrawBehavior>) {
+        let clip = ClipRect::from_image(image);
+        self.rasterize(line_method, &clip, &mut |x, y, coverage| {
+            set_coverage(image, x, y, color, coverage);
+        });
+    }
+
+    // Pixels reached by several segments (curve pieces, crossings) are handed
+    // to `plot` once with their highest coverage
+    pub fn rasterize<'a>(&self,
+                         line_method: impl Into<&'a dyn DrawBehavior>,
+                         clip: &ClipRect,
+                         plot: &mut dyn FnMut(i32, i32, f32)) {
+        let draw_behavior = line_method.into();
+        let mut pixels: HashMap<(i32, i32), f32> = HashMap::new();
+        for sub in self.flatten(PATH_TOLERANCE) {
+            let mut points: Vec<Vec2i> = Vec::with_capacity(sub.points.len() + 1);
+            for p in &sub.points {
+                let q = Vec2i::new(Scalar::new(p.x.value.floor() as i32),
+                                   Scalar::new(p.y.value.floor() as i32));
+                if points.last() != Some(&q) {points.push(q);}
+            }
+            if sub.closed && points.len() > 2 && points.last() != points.first() {points.push(points[0]);}
+            if points.len() == 1 {points.push(points[0]);}
+            polyline_pixels(&points, sub.closed, draw_behavior, &LinePatternEnum::SOLID, clip, &mut |x, y, coverage| {
+                let c = pixels.entry((x, y)).or_insert(0.0);
+                *c = c.max(coverage);
+            });
+        }
+        for ((x, y), coverage) in pixels {
+            plot(x, y, coverage);
+        }
+    }
+
+    // Thick stroke of every subpath, blended as a single shape
+    pub fn stroke_wide(&self,
+                       color : [u8; 4],
+                       stroke: &Stroke,
+                       image : &mut RgbaImage) {
+        let triangles: Vec<[Vec2f; 3]> = self.flatten(PATH_TOLERANCE).iter()
+            .flat_map(|sub| stroke.triangles(&sub.points, sub.closed))
+            .collect();
+        let mut mask = CoverageMask::new(&triangles, ClipRect::from_image(image));
+        for t in &triangles {
+            mask.triangle(t[0], t[1], t[2]);
+        }
+        mask.pixels(&mut |x, y, coverage| {blend(image, x, y, color, coverage);});
+    }
+
+    // Anti-aliased fill of the area enclosed by all subpaths, open ones
+    // closed implicitly
+    pub fn fill(&self,
+                color: [u8; 4],
+                rule : FillRuleEnum,
+                image: &mut RgbaImage) {
+        let clip = ClipRect::from_image(image);
+        self.fill_pixels(rule, &clip, &mut |x, y, coverage| {
+            blend(image, x, y, color, coverage);
+        });
+    }
+
+    pub fn fill_pixels(&self,
+                       rule: FillRuleEnum,
+                       clip: &ClipRect,
+                       plot: &mut dyn FnMut(i32, i32, f32)) {
+        let mut edges: Vec<(Vec2f, Vec2f)> = Vec::new();
+        for sub in self.flatten(PATH_TOLERANCE) {
+            let n = sub.points.len();
+            for i in 0..n {
+                let (a, b) = (sub.points[i], sub.points[(i + 1) % n]);
+                if a.y.value != b.y.value {edges.push((a, b));}
+            }
+        }
+        if edges.is_empty() {return;}
+
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for (a, b) in &edges {
+            x0 = x0.min(a.x.value).min(b.x.value);
+            x1 = x1.max(a.x.value).max(b.x.value);
+            y0 = y0.min(a.y.value).min(b.y.value);
+            y1 = y1.max(a.y.value).max(b.y.value);
+        }
+        let bounds = ClipRect::new(x0.floor() as i32, y0.floor() as i32,
+                                   x1.floor() as i32 + 1, y1.floor() as i32 + 1);
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        coverage_pixels(&bounds, clip, &mut |sy, spans| {
+            crossings.clear();
+            for (a, b) in &edges {
+                let (ay, by) = (a.y.value, b.y.value);
+                if (ay <= sy && sy < by) || (by <= sy && sy < ay) {
+                    let t = (sy - ay) / (by - ay);
+                    crossings.push((a.x.value + t * (b.x.value - a.x.value), if by > ay {1} else {-1}));
+                }
+            }
+            crossings.sort_by(|p, q| p.0.total_cmp(&q.0));
+
+            let mut winding = 0;
+            for k in 0..crossings.len() {
+                winding += crossings[k].1;
+                let inside = match rule {
+                    FillRuleEnum::NONZERO => winding != 0,
+                    FillRuleEnum::EVENODD => winding % 2 != 0,
+                };
+                if inside && k + 1 < crossings.len() {spans.push((crossings[k].0, crossings[k + 1].0));}
+            }
+        }, plot);
+    }
+}
+
+// Appends the horizontal extent of a region at height y as disjoint intervals
+pub(crate) type SpanFn<'a> = dyn FnMut(f32, &mut Vec<(f32, f32)>) + 'a;
+
+// Coverage of the region whose horizontal extent at height y is given by
+// `spans`, sampled on the COVERAGE_GRID. Path fills and the anti-aliased
+// shapes share it; each pixel is handed to `plot` once.
+pub(crate) fn coverage_pixels(bounds: &ClipRect,
+                              clip  : &ClipRect,
+                              spans : &mut SpanFn,
+                              plot  : &mut dyn FnMut(i32, i32, f32)) {
+    let bounds = bounds.intersect(clip);
+    if bounds.is_empty() {return;}
+    let grid    = COVERAGE_GRID;
+    let samples = (grid * grid) as f32;
+    let mut counts    = vec![0u32; (bounds.x1 - bounds.x0) as usize];
+    let mut row_spans = Vec::new();
+    for y in bounds.y0..bounds.y1 {
+        counts.iter_mut().for_each(|c| *c = 0);
+        for j in 0..grid {
+            row_spans.clear();
+            spans(y as f32 + (j as f32 + 0.5) / grid as f32, &mut row_spans);
+            for &(xa, xb) in &row_spans {
+                // Sample columns s with xa <= (s + 0.5) / grid < xb
+                let first = (xa * grid as f32 - 0.5).ceil() as i32;
+                let last  = (xb * grid as f32 - 0.5).ceil() as i32;
+                for s in first.max(bounds.x0 * grid)..last.min(bounds.x1 * grid) {
+                    counts[(s.div_euclid(grid) - bounds.x0) as usize] += 1;
+                }
+            }
+        }
+        for (i, count) in counts.iter().enumerate() {
+            if *count > 0 {plot(bounds.x0 + i as i32, y, *count as f32 / samples);}
+        }
+    }
+}
+
+// Appends the SVG arc from `p0` to `to` (endpoint parameterization, SVG 1.1
+// appendix F.6.5), excluding `p0`
+#[allow(clippy::too_many_arguments)]
+fn arc_points(p0       : Vec2f,
+              radii    : Vec2f,
+              rotation : f32,
+              large_arc: bool,
+              sweep    : bool,
+              to       : Vec2f,
+              tolerance: f32,
+              points   : &mut Vec<Vec2f>) {
+    let (mut rx, mut ry) = (radii.x.value.abs(), radii.y.value.abs());
+    if p0 == to {return;}
+    if rx == 0.0 || ry == 0.0 {
+        points.push(to);
+        return;
+    }
+    let (sin, cos) = rotation.sin_cos();
+    let hx = (p0.x.value - to.x.value) * 0.5;
+    let hy = (p0.y.value - to.y.value) * 0.5;
+    let x1 =  cos * hx + sin * hy;
+    let y1 = -sin * hx + cos * hy;
+
+    // Radii too small to reach are scaled up until they just do
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let sign = if large_arc == sweep {-1.0} else {1.0};
+    let coef = sign * (num / den).max(0.0).sqrt();
+    let (cx1, cy1) = (coef * rx * y1 / ry, -coef * ry * x1 / rx);
+    let cx = cos * cx1 - sin * cy1 + (p0.x.value + to.x.value) * 0.5;
+    let cy = sin * cx1 + cos * cy1 + (p0.y.value + to.y.value) * 0.5;
+
+    let angle = |ux: f32, uy: f32, vx: f32, vy: f32| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let theta = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle((x1 - cx1) / rx, (y1 - cy1) / ry, (-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    if !sweep && delta > 0.0 {delta -= 2.0 * PI;}
+    if sweep && delta < 0.0 {delta += 2.0 * PI;}
+
+    let radius = rx.max(ry);
+    let step = (1.0 - tolerance / radius).max(-1.0).acos() * 2.0;
+    let n = ((delta.abs() / step).ceil() as usize).clamp(1, 1024);
+    for i in 1..n {
+        let t = theta + delta * i as f32 / n as f32;
+        let (ex, ey) = (rx * t.cos(), ry * t.sin());
+        points.push(vec2(cx + cos * ex - sin * ey, cy + sin * ex + cos * ey));
+    }
+    points.push(to);
+}
@@ -0,0 +1,309 @@
+use image::{RgbaImage, Luma};
+use crate::{camera::Camera,
+            line::{lines, line_3d, project_segment, ClipRect, DrawBehavior},
+            model::{Mesh, TriangleMethodEnum, subpixel_triangle_pixels},
+            backend::{images::{set, srgb_to_linear, linear_to_srgb, DepthBuffer}, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    SHADED MESHES
+------------------------------------------------------------------------------*/
+// Meshes are lit in world space with a Lambert diffuse and a Blinn-Phong
+// specular term per light, summed in linear light and converted to sRGB when
+// written. Triangles are depth tested (smaller is nearer) and filled with the
+// top-left rule, so every pixel of a closed mesh is shaded once. Triangles are
+// clipped to the near and far planes in view space, like wireframe edges, and
+// what is left is drawn as a fan of up to three triangles.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShadingModelEnum {
+    // One color per face, lit at its center with the face normal
+    FLAT,
+    // Lit at the vertices, colors interpolated across the face
+    GOURAUD,
+    // Normals interpolated across the face and lit per pixel
+    PHONG,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LightEnum {
+    // Light from infinitely far away travelling along `direction`
+    DIRECTIONAL{direction: Vec3f, color: [f32; 3]},
+    // Light radiating from `position`, without falloff
+    POINT{position: Vec3f, color: [f32; 3]},
+}
+
+impl LightEnum {
+    // Unit vector from `p` toward the light, and its linear color
+    fn incident(&self, p: Vec3f) -> (Vec3f, [f32; 3]) {
+        match *self {
+            LightEnum::DIRECTIONAL{direction, color} => ((direction * Scalar::new(-1.0)).normalize(), color),
+            LightEnum::POINT{position, color}        => ((position - p).normalize(), color),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Material {
+    // sRGB surface color, alpha is written as is
+    pub color    : [u8; 4],
+    // Fraction of the surface color that shows without any light
+    pub ambient  : f32,
+    pub specular : f32,
+    pub shininess: f32,
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material{color: [255, 255, 255, 255], ambient: 0.1, specular: 0.5, shininess: 32.0}
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shading {
+    pub model   : ShadingModelEnum,
+    pub material: Material,
+    pub lights  : Vec<LightEnum>,
+}
+
+impl Shading {
+    pub fn new(model: ShadingModelEnum, material: Material) -> Shading {
+        Shading{model, material, lights: Vec::new()}
+    }
+
+    pub fn with_light(mut self, light: LightEnum) -> Shading {
+        self.lights.push(light);
+        self
+    }
+
+    // Linear color of point `p` with unit normal `n` seen from `eye`
+    pub fn lighting(&self, p: Vec3f, n: Vec3f, eye: Vec3f) -> [f32; 3] {
+        let albedo = [0, 1, 2].map(|c| srgb_to_linear(self.material.color[c] as f32 / 255.0));
+        let view = (eye - p).normalize();
+        let mut out = albedo.map(|a| a * self.material.ambient);
+        for light in &self.lights {
+            let (l, color) = light.incident(p);
+            let diffuse = n.dot(&l);
+            if diffuse <= 0.0 {continue;}
+            let half = l + view;
+            let specular = if half.norm() > 0.0 {
+                self.material.specular * n.dot(&half.normalize()).max(0.0).powf(self.material.shininess)
+            } else {0.0};
+            for c in 0..3 {
+                out[c] += color[c] * (albedo[c] * diffuse + specular);
+            }
+        }
+        out
+    }
+
+    fn encode(&self, linear: [f32; 3]) -> [u8; 4] {
+        let [r, g, b] = linear.map(|c| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8);
+        [r, g, b, self.material.color[3]]
+    }
+}
+
+fn weighted(values: [Vec3f; 3], w: [f32; 3]) -> Vec3f {
+    values[0] * Scalar::new(w[0]) + values[1] * Scalar::new(w[1]) + values[2] * Scalar::new(w[2])
+}
+
+// View-space corners of a triangle clipped to the near and far planes, each
+// with its weights over the original corners. Empty when nothing is left.
+fn clip_face(view: [Vec3f; 3], camera: &Camera) -> Vec<(Vec3f, [f32; 3])> {
+    let mut polygon = vec![(view[0], [1.0, 0.0, 0.0]), (view[1], [0.0, 1.0, 0.0]), (view[2], [0.0, 0.0, 1.0])];
+    for &(plane, keep_above) in &[(camera.near, true), (camera.far, false)] {
+        let distance = |v: Vec3f| if keep_above {v.z.value - plane} else {plane - v.z.value};
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for i in 0..polygon.len() {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            let (da, db) = (distance(a.0), distance(b.0));
+            if da >= 0.0 {clipped.push(a);}
+            if (da >= 0.0) != (db >= 0.0) {
+                let t = da / (da - db);
+                clipped.push((a.0 + (b.0 - a.0) * Scalar::new(t), [0, 1, 2].map(|k| a.1[k] + t * (b.1[k] - a.1[k]))));
+            }
+        }
+        polygon = clipped;
+    }
+    polygon
+}
+
+// Fills a projected triangle, handing `plot` each pixel with its depth and the
+// perspective-correct weights of the three corners
+fn rasterize_face(corners: [(Vec3f, f32); 3],
+                  clip   : &ClipRect,
+                  triangle_method: TriangleMethodEnum,
+                  plot   : &mut dyn FnMut(i32, i32, f32, [f32; 3])) {
+    let screen = corners.map(|c| c.0);
+    let area = (screen[1].x.value - screen[0].x.value) * (screen[2].y.value - screen[0].y.value)
+             - (screen[1].y.value - screen[0].y.value) * (screen[2].x.value - screen[0].x.value);
+    if area == 0.0 || !area.is_finite() {return;}
+
+    let to_2d = |v: Vec3f| Vec2f::new(v.x, v.y);
+    subpixel_triangle_pixels(to_2d(screen[0]), to_2d(screen[1]), to_2d(screen[2]), triangle_method, clip, &mut |x, y| {
+        // Screen-space barycentrics of the pixel center
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let opposite = |a: Vec3f, b: Vec3f| {
+            ((b.x.value - a.x.value) * (py - a.y.value) - (b.y.value - a.y.value) * (px - a.x.value)) / area
+        };
+        let b = [opposite(screen[1], screen[2]), opposite(screen[2], screen[0]), opposite(screen[0], screen[1])];
+        let d = b[0] * screen[0].z.value + b[1] * screen[1].z.value + b[2] * screen[2].z.value;
+
+        // Depth is linear on screen, other attributes only after dividing by z
+        let w = [b[0] / corners[0].1, b[1] / corners[1].1, b[2] / corners[2].1];
+        let sum = w[0] + w[1] + w[2];
+        plot(x, y, d, w.map(|v| v / sum));
+    });
+}
+
+// Clips a triangle given by its view-space corners and fills what is left,
+// handing `plot` each pixel with its depth and the perspective-correct weights
+// of the original corners
+fn rasterize_view_face(view   : [Vec3f; 3],
+                       camera : &Camera,
+                       clip   : &ClipRect,
+                       size   : (u32, u32),
+                       triangle_method: TriangleMethodEnum,
+                       plot   : &mut dyn FnMut(i32, i32, f32, [f32; 3])) {
+    let polygon = clip_face(view, camera);
+    for i in 1..polygon.len().saturating_sub(1) {
+        let fan = [polygon[0], polygon[i], polygon[i + 1]];
+        let corners = fan.map(|(v, _)| (camera.project_view(v, size.0, size.1), v.z.value));
+        rasterize_face(corners, clip, triangle_method, &mut |x, y, d, w| {
+            plot(x, y, d, [0, 1, 2].map(|k| w[0] * fan[0].1[k] + w[1] * fan[1].1[k] + w[2] * fan[2].1[k]));
+        });
+    }
+}
+
+pub fn render_mesh(mesh   : &Mesh,
+                   camera : &Camera,
+                   shading: &Shading,
+                   image  : &mut RgbaImage,
+                   depth  : &mut DepthBuffer,
+                   triangle_method: TriangleMethodEnum) {
+    let (width, height) = image.dimensions();
+    let clip = ClipRect::from_image(image).intersect(&depth_clip(depth));
+    let eye  = camera.eye;
+    let view = mesh.positions.iter().map(|&p| camera.to_view(p)).collect::<Vec<_>>();
+    // Vertex normals that cancelled out fall back to the face normal
+    let vertex_normal = |i: usize, face: Vec3f| {
+        if mesh.normals.is_empty() || mesh.normals[i].norm() == 0.0 {face} else {mesh.normals[i]}
+    };
+
+    for (t, &corners) in mesh.triangles.iter().enumerate() {
+        let face = mesh.face_normal(t);
+        if face.norm() == 0.0 {continue;}
+        let positions = corners.map(|i| mesh.positions[i]);
+        let normals   = corners.map(|i| vertex_normal(i, face));
+        let flat = match shading.model {
+            ShadingModelEnum::FLAT => {
+                let center = weighted(positions, [1.0 / 3.0; 3]);
+                shading.encode(shading.lighting(center, face, eye))
+            },
+            _ => [0; 4],
+        };
+        let colors = match shading.model {
+            ShadingModelEnum::GOURAUD => [0, 1, 2].map(|k| shading.lighting(positions[k], normals[k], eye)),
+            _ => [[0.0; 3]; 3],
+        };
+
+        rasterize_view_face(corners.map(|i| view[i]), camera, &clip, (width, height), triangle_method, &mut |x, y, d, w| {
+            if d >= depth[(x as u32, y as u32)].0[0] {return;}
+            let color = match shading.model {
+                ShadingModelEnum::FLAT    => flat,
+                ShadingModelEnum::GOURAUD => shading.encode([0, 1, 2].map(|c| {
+                    w[0] * colors[0][c] + w[1] * colors[1][c] + w[2] * colors[2][c]
+                })),
+                ShadingModelEnum::PHONG   => {
+                    let n = weighted(normals, w);
+                    let n = if n.norm() > 0.0 {n.normalize()} else {face};
+                    shading.encode(shading.lighting(weighted(positions, w), n, eye))
+                },
+            };
+            depth[(x as u32, y as u32)] = Luma([d]);
+            set(image, x, y, color);
+        });
+    }
+}
+
+/*------------------------------------------------------------------------------
+                    WIREFRAMES
+------------------------------------------------------------------------------*/
+// Every edge of a mesh is drawn once through the line module. With
+// `cull_hidden` the mesh surface is first written into `depth`, leaving the
+// image alone, and edges are depth tested against it so only visible ones
+// show. To overlay a wireframe on the shaded mesh, call `render_mesh` and then
+// `render_wireframe` with the same depth buffer.
+
+#[derive(Copy, Clone)]
+pub struct Wireframe<'a> {
+    pub color      : [u8; 4],
+    pub method     : &'a dyn DrawBehavior,
+    pub cull_hidden: bool,
+    // Fraction of their view distance by which edges are moved toward the
+    // viewer, so they win against their own faces
+    pub bias       : f32,
+}
+
+impl<'a> Wireframe<'a> {
+    pub fn new(color: [u8; 4], method: impl Into<&'a dyn DrawBehavior>) -> Wireframe<'a> {
+        Wireframe{color, method: method.into(), cull_hidden: false, bias: 1e-3}
+    }
+
+    pub fn culled(mut self) -> Wireframe<'a> {
+        self.cull_hidden = true;
+        self
+    }
+}
+
+fn depth_clip(depth: &DepthBuffer) -> ClipRect {
+    ClipRect::new(0, 0, depth.width() as i32, depth.height() as i32)
+}
+
+// Writes the nearest surface depth of the mesh without shading anything
+pub fn render_depth(mesh  : &Mesh,
+                    camera: &Camera,
+                    depth : &mut DepthBuffer,
+                    triangle_method: TriangleMethodEnum) {
+    let size = depth.dimensions();
+    depth_pass(mesh, camera, size, depth, triangle_method);
+}
+
+// Depth of the mesh projected onto a `size` image, kept where the buffer
+// reaches
+fn depth_pass(mesh  : &Mesh,
+              camera: &Camera,
+              size  : (u32, u32),
+              depth : &mut DepthBuffer,
+              triangle_method: TriangleMethodEnum) {
+    let clip = ClipRect::new(0, 0, size.0 as i32, size.1 as i32).intersect(&depth_clip(depth));
+    let view = mesh.positions.iter().map(|&p| camera.to_view(p)).collect::<Vec<_>>();
+    for corners in &mesh.triangles {
+        rasterize_view_face(corners.map(|i| view[i]), camera, &clip, size, triangle_method, &mut |x, y, d, _| {
+            let stored = &mut depth[(x as u32, y as u32)].0[0];
+            if d < *stored {*stored = d;}
+        });
+    }
+}
+
+pub fn render_wireframe(mesh     : &Mesh,
+                        camera   : &Camera,
+                        wireframe: &Wireframe,
+                        image    : &mut RgbaImage,
+                        depth    : &mut DepthBuffer) {
+    let (width, height) = image.dimensions();
+    if wireframe.cull_hidden {
+        // Both triangle methods cover the same pixels, so either will do.
+        // Faces are projected like the edges, onto the image.
+        depth_pass(mesh, camera, (width, height), depth, TriangleMethodEnum::EDGEFUNCTION);
+    }
+    let pixel = |s: Vec3f| Vec2i::new(Scalar::new(s.x.value.floor() as i32), Scalar::new(s.y.value.floor() as i32));
+    let mut segments = Vec::new();
+    for [a, b] in mesh.edges() {
+        let (p0, p1) = (mesh.positions[a], mesh.positions[b]);
+        if wireframe.cull_hidden {
+            line_3d(p0, p1, wireframe.color, camera, image, depth, wireframe.method, wireframe.bias);
+        } else if let Some((s0, s1)) = project_segment(p0, p1, camera, width, height) {
+            segments.push((pixel(s0), pixel(s1)));
+        }
+    }
+    lines(&segments, &[wireframe.color], image, wireframe.method);
+}
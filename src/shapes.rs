@@ -0,0 +1,483 @@
+use image::RgbaImage;
+use crate::{line::ClipRect,
+            path::coverage_pixels,
+            backend::{images::{set, blend}, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    MIDPOINT CIRCLES AND ELLIPSES
+------------------------------------------------------------------------------*/
+// Integer shapes are centered on a pixel, like `line`. Only one octant (or
+// quadrant) is computed; the rest comes from symmetry. Instead of stepping
+// along the outline, each point is solved from the midpoint decision for its
+// row or column, so only rows and columns inside the clip are visited. Filled
+// shapes cover every pixel between the outline's extremes on each row,
+// outline included. Every pixel is plotted once.
+
+// Distinct mirror images of (x, y) about the center
+fn symmetric(x: i64, y: i64, points: &mut Vec<(i64, i64)>) {
+    points.clear();
+    points.extend_from_slice(&[(x, y), (-x, y), (x, -y), (-x, -y)]);
+    points.sort_unstable();
+    points.dedup();
+}
+
+// Last value in lo..=hi for which `test` holds, or lo - 1 if none does;
+// `test` must hold on a prefix of the range
+fn last_true(lo: i64, hi: i64, test: impl Fn(i64) -> bool) -> i64 {
+    let (mut lo, mut hi) = (lo, hi + 1);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if test(mid) {lo = mid + 1;} else {hi = mid;}
+    }
+    lo - 1
+}
+
+// Offsets 0..=max at which c + d or c - d lies in lo..hi; either range may
+// be empty
+fn offsets(c: i32, lo: i32, hi: i32, max: i64) -> [(i64, i64); 2] {
+    let (c, lo, hi) = (c as i64, lo as i64, hi as i64);
+    [((lo - c).max(0), (hi - 1 - c).min(max)), ((c - hi + 1).max(0), (c - lo).min(max))]
+}
+
+// Sorted, disjoint union of the non-empty inclusive ranges
+fn union(ranges: &[(i64, i64)]) -> Vec<(i64, i64)> {
+    let mut ranges: Vec<_> = ranges.iter().copied().filter(|&(a, b)| a <= b).collect();
+    ranges.sort_unstable();
+    let mut out: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+    for (a, b) in ranges {
+        match out.last_mut() {
+            Some(last) if a <= last.1 + 1 => last.1 = last.1.max(b),
+            _ => out.push((a, b)),
+        }
+    }
+    out
+}
+
+fn plot_clipped(clip: &ClipRect, x: i64, y: i64, plot: &mut dyn FnMut(i32, i32)) {
+    let inside = x >= clip.x0 as i64 && x < clip.x1 as i64 && y >= clip.y0 as i64 && y < clip.y1 as i64;
+    if inside {plot(x as i32, y as i32);}
+}
+
+// First octant (x >= y) of the midpoint circle. The walk starting at (r, 0)
+// with d = 1 - r keeps x on the next row while (x - 1/2)^2 + y^2 < r^2 + 1/4,
+// so row y holds the largest such x.
+struct CircleOctant {
+    radius: i64,
+    end   : i64,
+}
+
+impl CircleOctant {
+    fn new(radius: i64) -> CircleOctant {
+        let mut octant = CircleOctant{radius, end: 0};
+        octant.end = last_true(0, radius, |y| octant.x(y) >= y);
+        octant
+    }
+
+    fn x(&self, y: i64) -> i64 {
+        if self.radius == 0 {return if y == 0 {0} else {-1};}
+        let (r2, y2) = (self.radius as i128 * self.radius as i128, y as i128 * y as i128);
+        last_true(0, self.radius, |x| x as i128 * (x as i128 - 1) + y2 < r2)
+    }
+
+    // Widest outline offset on row dy, counting the mirrored second octant
+    fn half_width(&self, dy: i64) -> i64 {
+        if dy <= self.end {self.x(dy)} else {last_true(0, self.end, |y| self.x(y) >= dy)}
+    }
+}
+
+// First quadrant of the midpoint ellipse, in two regions split where the
+// slope passes -1. In region 1 the walk has one point per column x < split:
+// with decisions scaled by 4 to stay integral, y drops once the midpoint
+// (x, y - 1/2) is no longer inside. Region 2 has one point per row y <= top,
+// moving right once (x + 1/2, y) is no longer outside.
+struct EllipseQuadrant {
+    rx   : i64,
+    ry   : i64,
+    rx2  : i128,
+    ry2  : i128,
+    split: i64,
+    top  : i64,
+}
+
+impl EllipseQuadrant {
+    fn new(rx: i64, ry: i64) -> EllipseQuadrant {
+        let (rx2, ry2) = (rx as i128 * rx as i128, ry as i128 * ry as i128);
+        let mut quadrant = EllipseQuadrant{rx, ry, rx2, ry2, split: rx + 1, top: -1};
+        if ry == 0 {return quadrant;}
+        quadrant.split = last_true(0, rx, |x| ry2 * (x as i128) < rx2 * quadrant.y(x) as i128) + 1;
+        // Region 2 starts where the last region 1 step lands, at most one row
+        // down
+        let last = quadrant.split - 1;
+        quadrant.top = if last < 0 {ry} else {
+            let y = quadrant.y(last);
+            if quadrant.keeps_row(last + 1, y) {y} else {y - 1}
+        };
+        quadrant
+    }
+
+    // Whether the midpoint (x, y - 1/2) is inside
+    fn keeps_row(&self, x: i64, y: i64) -> bool {
+        let (x, y) = (x as i128, y as i128);
+        4 * self.ry2 * x * x + self.rx2 * (2 * y - 1) * (2 * y - 1) < 4 * self.rx2 * self.ry2
+    }
+
+    // Region 1 row of column x
+    fn y(&self, x: i64) -> i64 {
+        if self.ry == 0 {return 0;}
+        if x == 0 {return self.ry;}
+        last_true(0, self.ry, |y| self.keeps_row(x, y))
+    }
+
+    // Region 2 column of row y
+    fn x(&self, y: i64) -> i64 {
+        if y == self.top {return self.split;}
+        let inside = 4 * self.rx2 * self.ry2 - 4 * self.rx2 * y as i128 * y as i128;
+        let x = last_true(0, self.rx, |x| self.ry2 * (2 * x as i128 + 1) * (2 * x as i128 + 1) <= inside) + 1;
+        x.max(self.split)
+    }
+
+    fn half_width(&self, dy: i64) -> i64 {
+        if dy <= self.top {self.x(dy)} else {last_true(0, self.split - 1, |x| self.y(x) >= dy)}
+    }
+}
+
+pub(crate) fn circle_pixels(center: Vec2i, radius: i32, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+    if radius < 0 {return;}
+    let (cx, cy) = (center.x.value, center.y.value);
+    let octant = CircleOctant::new(radius as i64);
+    // Octant row y lands on rows cy +- y, and mirrored on columns cx +- y
+    let [a, b] = offsets(cy, clip.y0, clip.y1, octant.end);
+    let [c, d] = offsets(cx, clip.x0, clip.x1, octant.end);
+    let mut points = Vec::with_capacity(8);
+    for (lo, hi) in union(&[a, b, c, d]) {
+        for y in lo..=hi {
+            let x = octant.x(y);
+            symmetric(x, y, &mut points);
+            if x != y {
+                for i in 0..points.len() {
+                    let (a, b) = points[i];
+                    points.push((b, a));
+                }
+            }
+            for &(dx, dy) in &points {
+                plot_clipped(clip, cx as i64 + dx, cy as i64 + dy, plot);
+            }
+        }
+    }
+}
+
+pub(crate) fn ellipse_pixels(center: Vec2i, rx: i32, ry: i32, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+    if rx < 0 || ry < 0 {return;}
+    let (cx, cy) = (center.x.value, center.y.value);
+    let quadrant = EllipseQuadrant::new(rx as i64, ry as i64);
+    let mut points = Vec::with_capacity(4);
+    let mut mirror = |x: i64, y: i64, plot: &mut dyn FnMut(i32, i32)| {
+        symmetric(x, y, &mut points);
+        for &(dx, dy) in &points {
+            plot_clipped(clip, cx as i64 + dx, cy as i64 + dy, plot);
+        }
+    };
+    // Region 1 points only reach the clip through their columns, region 2
+    // points through their rows
+    for (lo, hi) in union(&offsets(cx, clip.x0, clip.x1, quadrant.split - 1)) {
+        for x in lo..=hi {
+            mirror(x, quadrant.y(x), plot);
+        }
+    }
+    for (lo, hi) in union(&offsets(cy, clip.y0, clip.y1, quadrant.top)) {
+        for y in lo..=hi {
+            mirror(quadrant.x(y), y, plot);
+        }
+    }
+}
+
+// Spans of the given half-width on each row within `height` of the center
+fn fill_rows(center: Vec2i, height: i64, half: &dyn Fn(i64) -> i64, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+    let (cx, cy) = (center.x.value as i64, center.y.value as i64);
+    for y in (cy - height).max(clip.y0 as i64)..(cy + height + 1).min(clip.y1 as i64) {
+        let w = half((y - cy).abs());
+        for x in (cx - w).max(clip.x0 as i64)..(cx + w + 1).min(clip.x1 as i64) {
+            plot(x as i32, y as i32);
+        }
+    }
+}
+
+pub(crate) fn circle_filled_pixels(center: Vec2i, radius: i32, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+    if radius < 0 {return;}
+    let octant = CircleOctant::new(radius as i64);
+    fill_rows(center, radius as i64, &|dy| octant.half_width(dy), clip, plot);
+}
+
+pub(crate) fn ellipse_filled_pixels(center: Vec2i, rx: i32, ry: i32, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+    if rx < 0 || ry < 0 {return;}
+    let quadrant = EllipseQuadrant::new(rx as i64, ry as i64);
+    fill_rows(center, ry as i64, &|dy| quadrant.half_width(dy), clip, plot);
+}
+
+pub fn circle(center: Vec2i, radius: i32, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    circle_pixels(center, radius, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+pub fn circle_filled(center: Vec2i, radius: i32, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    circle_filled_pixels(center, radius, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+pub fn ellipse(center: Vec2i, rx: i32, ry: i32, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    ellipse_pixels(center, rx, ry, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+pub fn ellipse_filled(center: Vec2i, rx: i32, ry: i32, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    ellipse_filled_pixels(center, rx, ry, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+/*------------------------------------------------------------------------------
+                    ARCS AND SECTORS
+------------------------------------------------------------------------------*/
+// Angles are in radians from +x toward +y, which is clockwise on screen since
+// image y points down. A sector runs from `start` up to, but not including,
+// `end`; sweeps of a full turn or more cover the whole circle. Membership is
+// decided per pixel from cross products with the two boundary directions, so
+// sectors that share a boundary angle split its pixels exactly, with no gaps
+// or overlap. The center pixel belongs to the sector containing angle 0.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AngleRange {
+    start: (f64, f64),
+    end  : (f64, f64),
+    sweep: f64,
+}
+
+impl AngleRange {
+    pub fn new(start: f32, end: f32) -> AngleRange {
+        let tau   = 2.0 * std::f64::consts::PI;
+        let delta = end as f64 - start as f64;
+        let sweep = if delta.abs() >= tau {tau} else {delta.rem_euclid(tau)};
+        AngleRange{start: direction(start), end: direction(end), sweep}
+    }
+
+    // Whether the direction (dx, dy) from the center lies in the range
+    pub fn contains(&self, dx: f64, dy: f64) -> bool {
+        let (dx, dy) = if dx == 0.0 && dy == 0.0 {(1.0, 0.0)} else {(dx, dy)};
+        if self.sweep >= 2.0 * std::f64::consts::PI {return true;}
+        if self.sweep == 0.0 {return false;}
+        let cross = |d: (f64, f64)| d.0 * dy - d.1 * dx;
+        let dot   = |d: (f64, f64)| d.0 * dx + d.1 * dy;
+        let (a, b) = (cross(self.start), cross(self.end));
+        // Branch on the snapped directions rather than the sweep, which may
+        // round to the other side of a half turn
+        let (s, e) = (self.start, self.end);
+        let turn = s.0 * e.1 - s.1 * e.0;
+        if turn == 0.0 && s.0 * e.0 + s.1 * e.1 > 0.0 {
+            return self.sweep > std::f64::consts::PI;
+        }
+        if turn >= 0.0 {
+            // Past the start ray (or on it) and strictly before the end ray
+            (a > 0.0 || (a == 0.0 && dot(self.start) > 0.0)) && (b < 0.0 || (b == 0.0 && dot(self.end) < 0.0))
+        } else {
+            // Everything except the gap from the end ray back to the start
+            !((b > 0.0 || (b == 0.0 && dot(self.end) > 0.0)) && a < 0.0)
+        }
+    }
+}
+
+// Unit direction snapped to a 2^-20 grid, so angles like PI / 4 that are not
+// exact in f32 still land on the pixel diagonal
+fn direction(angle: f32) -> (f64, f64) {
+    let snap = |v: f64| (v * 1048576.0).round() / 1048576.0;
+    let (sin, cos) = (angle as f64).sin_cos();
+    (snap(cos), snap(sin))
+}
+
+pub(crate) fn arc_pixels(center: Vec2i, radius: i32, range: &AngleRange, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+    let (cx, cy) = (center.x.value as f64, center.y.value as f64);
+    circle_pixels(center, radius, clip, &mut |x, y| {
+        if range.contains(x as f64 - cx, y as f64 - cy) {plot(x, y);}
+    });
+}
+
+// Pixels of the filled circle of `outer` that are outside the filled circle
+// one smaller than `inner`, so both outlines are part of the ring
+pub(crate) fn sector_pixels(center: Vec2i,
+                            inner : i32,
+                            outer : i32,
+                            range : &AngleRange,
+                            clip  : &ClipRect,
+                            plot  : &mut dyn FnMut(i32, i32)) {
+    if outer < 0 || inner > outer {return;}
+    let (cx, cy) = (center.x.value as i64, center.y.value as i64);
+    let outer_half = CircleOctant::new(outer as i64);
+    let inner_half = CircleOctant::new(inner as i64 - 1);
+    // Column offsets inside the clip; the angle test only sees those
+    let (dx0, dx1) = (clip.x0 as i64 - cx, clip.x1 as i64 - 1 - cx);
+    for y in (cy - outer as i64).max(clip.y0 as i64)..(cy + outer as i64 + 1).min(clip.y1 as i64) {
+        let dy = y - cy;
+        let w = outer_half.half_width(dy.abs());
+        let hole = if dy.abs() < inner as i64 {inner_half.half_width(dy.abs())} else {-1};
+        for (lo, hi) in [(-w, (-hole - 1).min(-1)), (hole + 1, w)] {
+            for dx in lo.max(dx0)..=hi.min(dx1) {
+                if range.contains(dx as f64, dy as f64) {plot((cx + dx) as i32, y as i32);}
+            }
+        }
+    }
+}
+
+pub fn arc(center: Vec2i, radius: i32, start: f32, end: f32, color: [u8; 4], image: &mut RgbaImage) {
+    let clip  = ClipRect::from_image(image);
+    let range = AngleRange::new(start, end);
+    arc_pixels(center, radius, &range, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+pub fn pie(center: Vec2i, radius: i32, start: f32, end: f32, color: [u8; 4], image: &mut RgbaImage) {
+    annular_sector(center, 0, radius, start, end, color, image);
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn annular_sector(center: Vec2i,
+                      inner : i32,
+                      outer : i32,
+                      start : f32,
+                      end   : f32,
+                      color : [u8; 4],
+                      image : &mut RgbaImage) {
+    let clip  = ClipRect::from_image(image);
+    let range = AngleRange::new(start, end);
+    sector_pixels(center, inner, outer, &range, &clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+/*------------------------------------------------------------------------------
+                    ROTATED AND ANTI-ALIASED ELLIPSES
+------------------------------------------------------------------------------*/
+// Continuous pixel coordinates, like strokes and paths: pixel (x, y) covers
+// [x, x + 1) x [y, y + 1). `angle` turns the x radius from the +x axis toward
+// +y, in radians. Each row is solved from the ellipse's implicit equation, so
+// no polyline approximation is involved.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ellipse {
+    pub center: Vec2f,
+    pub radii : Vec2f,
+    pub angle : f32,
+}
+
+impl Ellipse {
+    pub fn new(center: Vec2f, radii: Vec2f, angle: f32) -> Ellipse {
+        Ellipse{center, radii, angle}
+    }
+
+    pub fn circle(center: Vec2f, radius: f32) -> Ellipse {
+        Ellipse::new(center, Vec2f::new(Scalar::new(radius), Scalar::new(radius)), 0.0)
+    }
+
+    // Same center and angle, radii grown by `d` (shrunk for negative `d`)
+    pub fn grown(&self, d: f32) -> Ellipse {
+        let radii = Vec2f::new(Scalar::new(self.radii.x.value + d), Scalar::new(self.radii.y.value + d));
+        Ellipse{radii, ..*self}
+    }
+
+    // Horizontal extent of the interior along the line at height `y`
+    pub fn span(&self, y: f32) -> Option<(f32, f32)> {
+        let (rx, ry) = (self.radii.x.value, self.radii.y.value);
+        if !(rx > 0.0 && ry > 0.0) {return None;}
+        let (s, c) = self.angle.sin_cos();
+        let (irx, iry) = (1.0 / (rx * rx), 1.0 / (ry * ry));
+        // a dx^2 + b dx dy + c dy^2 <= 1
+        let a  = c * c * irx + s * s * iry;
+        let b  = 2.0 * c * s * (irx - iry);
+        let cc = s * s * irx + c * c * iry;
+        let dy = y - self.center.y.value;
+        let disc = (b * dy) * (b * dy) - 4.0 * a * (cc * dy * dy - 1.0);
+        if disc < 0.0 {return None;}
+        let root = disc.sqrt();
+        let cx = self.center.x.value;
+        Some((cx + (-b * dy - root) / (2.0 * a), cx + (-b * dy + root) / (2.0 * a)))
+    }
+
+    // Pixel bounds of the ellipse
+    pub fn bounds(&self) -> ClipRect {
+        let (s, c) = self.angle.sin_cos();
+        let (rx, ry) = (self.radii.x.value.abs(), self.radii.y.value.abs());
+        let ex = (rx * c).hypot(ry * s);
+        let ey = (rx * s).hypot(ry * c);
+        let (cx, cy) = (self.center.x.value, self.center.y.value);
+        ClipRect::new((cx - ex).floor() as i32, (cy - ey).floor() as i32,
+                      (cx + ex).floor() as i32 + 1, (cy + ey).floor() as i32 + 1)
+    }
+
+    // Pixels whose centers are inside, one span per row
+    pub(crate) fn filled_pixels(&self, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+        let bounds = self.bounds().intersect(clip);
+        for y in bounds.y0..bounds.y1 {
+            if let Some((x0, x1)) = self.row(y) {
+                for x in x0.max(bounds.x0)..=x1.min(bounds.x1 - 1) {
+                    plot(x, y);
+                }
+            }
+        }
+    }
+
+    // Filled pixels with a 4-neighbour outside, so the outline is 8-connected
+    pub(crate) fn outline_pixels(&self, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32)) {
+        let bounds = self.bounds().intersect(clip);
+        for y in bounds.y0..bounds.y1 {
+            let Some((x0, x1)) = self.row(y) else {continue;};
+            let (above, below) = (self.row(y - 1), self.row(y + 1));
+            let inside = |row: Option<(i32, i32)>, x: i32| row.is_some_and(|(a, b)| a <= x && x <= b);
+            for x in x0.max(bounds.x0)..=x1.min(bounds.x1 - 1) {
+                if x == x0 || x == x1 || !inside(above, x) || !inside(below, x) {plot(x, y);}
+            }
+        }
+    }
+
+    fn row(&self, y: i32) -> Option<(i32, i32)> {
+        let (lo, hi) = self.span(y as f32 + 0.5)?;
+        let (x0, x1) = ((lo - 0.5).ceil() as i32, (hi - 0.5).floor() as i32);
+        if x0 <= x1 {Some((x0, x1))} else {None}
+    }
+}
+
+pub fn ellipse_rotated(ellipse: &Ellipse, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    ellipse.outline_pixels(&clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+pub fn ellipse_rotated_filled(ellipse: &Ellipse, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    ellipse.filled_pixels(&clip, &mut |x, y| {set(image, x, y, color);});
+}
+
+// Anti-aliased filled ellipse: coverage from the same sub-pixel grid as path
+// fills
+pub fn ellipse_aa_filled(ellipse: &Ellipse, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    coverage_pixels(&ellipse.bounds(), &clip, &mut |y, spans| spans.extend(ellipse.span(y)),
+                    &mut |x, y, coverage| {blend(image, x, y, color, coverage);});
+}
+
+// Anti-aliased outline `width` pixels wide, centered on the ellipse
+pub fn ellipse_aa(ellipse: &Ellipse, width: f32, color: [u8; 4], image: &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    let (outer, inner) = (ellipse.grown(width * 0.5), ellipse.grown(-width * 0.5));
+    coverage_pixels(&outer.bounds(), &clip, &mut |y, spans| ring_spans(&outer, &inner, y, spans),
+                    &mut |x, y, coverage| {blend(image, x, y, color, coverage);});
+}
+
+pub fn circle_aa(center: Vec2f, radius: f32, width: f32, color: [u8; 4], image: &mut RgbaImage) {
+    ellipse_aa(&Ellipse::circle(center, radius), width, color, image);
+}
+
+pub fn circle_aa_filled(center: Vec2f, radius: f32, color: [u8; 4], image: &mut RgbaImage) {
+    ellipse_aa_filled(&Ellipse::circle(center, radius), color, image);
+}
+
+// Parts of the outer span not inside the inner one
+pub(crate) fn ring_spans(outer: &Ellipse, inner: &Ellipse, y: f32, spans: &mut Vec<(f32, f32)>) {
+    let Some((a, b)) = outer.span(y) else {return;};
+    match inner.span(y) {
+        Some((c, d)) => spans.extend_from_slice(&[(a, c), (d, b)]),
+        None         => spans.push((a, b)),
+    }
+}
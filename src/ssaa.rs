@@ -0,0 +1,209 @@
+use std::f32::consts::PI;
+use image::{RgbaImage, Rgba};
+use crate::{line::{line, DrawBehavior},
+            model::{triangle_subpixel, TriangleMethodEnum},
+            backend::{images::set_all, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    SUPERSAMPLING ANTI-ALIASING (SSAA)
+------------------------------------------------------------------------------*/
+// Primitives are drawn into a buffer `factor` times larger in each direction
+// and filtered back down to the target size. Target pixel (x, y) covers the
+// high resolution block starting at (x * factor, y * factor).
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ResolveFilterEnum {
+    BOX,        // average of the pixel's own samples
+    TENT,       // linear falloff, radius 1 pixel
+    MITCHELL,   // Mitchell-Netravali cubic, B = C = 1/3, radius 2 pixels
+    LANCZOS,    // Lanczos-3, radius 3 pixels
+}
+
+impl ResolveFilterEnum {
+    // Support radius in target pixels
+    pub fn radius(&self) -> f32 {
+        match self {
+            ResolveFilterEnum::BOX      => 0.5,
+            ResolveFilterEnum::TENT     => 1.0,
+            ResolveFilterEnum::MITCHELL => 2.0,
+            ResolveFilterEnum::LANCZOS  => 3.0,
+        }
+    }
+
+    // Filter weight at distance `x` (in target pixels) from the pixel center
+    pub fn weight(&self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            ResolveFilterEnum::BOX => if x <= 0.5 {1.0} else {0.0},
+            ResolveFilterEnum::TENT => (1.0 - x).max(0.0),
+            ResolveFilterEnum::MITCHELL => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                     + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                     + (6.0 - 2.0 * b)) / 6.0
+                } else if x < 2.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                     + (6.0 * b + 30.0 * c) * x * x
+                     + (-12.0 * b - 48.0 * c) * x
+                     + (8.0 * b + 24.0 * c)) / 6.0
+                } else {0.0}
+            }
+            ResolveFilterEnum::LANCZOS => {
+                if x < 1e-6 {1.0}
+                else if x < 3.0 {3.0 * (PI * x).sin() * (PI * x / 3.0).sin() / (PI * PI * x * x)}
+                else {0.0}
+            }
+        }
+    }
+}
+
+pub struct Supersampler {
+    width : u32,
+    height: u32,
+    factor: u32,
+    image : RgbaImage,
+}
+
+impl Supersampler {
+    pub fn new(width: u32, height: u32, factor: u32) -> Supersampler {
+        let factor = factor.max(1);
+        Supersampler{width, height, factor, image: RgbaImage::new(width * factor, height * factor)}
+    }
+
+    pub fn factor(&self) -> u32 {
+        self.factor
+    }
+
+    // The high resolution buffer, for drawing with any routine directly
+    pub fn image(&mut self) -> &mut RgbaImage {
+        &mut self.image
+    }
+
+    pub fn clear(&mut self, color: [u8; 4]) {
+        set_all(&mut self.image, color);
+    }
+
+    // Center of target pixel `p` in high resolution coordinates, where lines
+    // run, saturating at the i32 range
+    pub fn scale(&self, p: Vec2i) -> Vec2i {
+        let (x, y) = self.scale_wide(p);
+        let fit = |c: i64| c.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+        Vec2i::new(Scalar::new(fit(x)), Scalar::new(fit(y)))
+    }
+
+    fn scale_wide(&self, p: Vec2i) -> (i64, i64) {
+        let (f, h) = (self.factor as i64, self.factor as i64 / 2);
+        (p.x.value as i64 * f + h, p.y.value as i64 * f + h)
+    }
+
+    // High resolution endpoints as they are when they fit comfortably in an
+    // i32, otherwise clipped (in f64) to the buffer plus a margin for the
+    // minor axis offsets. None when nothing of the segment is left.
+    fn fit_segment(&self, a: (i64, i64), b: (i64, i64)) -> Option<(Vec2i, Vec2i)> {
+        let point = |x: i64, y: i64| Vec2i::new(Scalar::new(x as i32), Scalar::new(y as i32));
+        let limit = (i32::MAX / 2) as i64;
+        if [a.0, a.1, b.0, b.1].iter().all(|c| c.abs() <= limit) {
+            return Some((point(a.0, a.1), point(b.0, b.1)));
+        }
+        let margin = self.factor as f64 + 1.0;
+        let (x0, y0) = (a.0 as f64, a.1 as f64);
+        let (dx, dy) = (b.0 as f64 - x0, b.1 as f64 - y0);
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+        // Liang-Barsky: p * t <= q on each side
+        for &(p, q) in &[(-dx, x0 + margin), (dx, self.image.width() as f64 + margin - x0),
+                         (-dy, y0 + margin), (dy, self.image.height() as f64 + margin - y0)] {
+            if p == 0.0 {
+                if q < 0.0 {return None;}
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+        if t0 > t1 {return None;}
+        let at = |t: f64| point((x0 + t * dx).round() as i64, (y0 + t * dy).round() as i64);
+        Some((at(t0), at(t1)))
+    }
+
+    // A one pixel line at target resolution is `factor` lines side by side,
+    // offset across the minor axis so the stroke keeps its apparent width.
+    pub fn line<'a>(&mut self,
+                    p0   : Vec2i,
+                    p1   : Vec2i,
+                    color: [u8; 4],
+                    line_method: impl Into<&'a dyn DrawBehavior>) {
+        let draw_behavior = line_method.into();
+        let (f, h) = (self.factor as i32, self.factor as i32 / 2);
+        let (a, b) = (self.scale_wide(p0), self.scale_wide(p1));
+        let steep = (b.0 - a.0).abs() < (b.1 - a.1).abs();
+        let Some((s0, s1)) = self.fit_segment(a, b) else {return;};
+        for k in 0..f {
+            let offset = if steep {Vec2i::new(Scalar::new(k - h), Scalar::new(0))}
+                         else {Vec2i::new(Scalar::new(0), Scalar::new(k - h))};
+            line(s0 + offset, s1 + offset, color, &mut self.image, draw_behavior);
+        }
+    }
+
+    // Triangle vertices sit on pixel corners, so they scale to block corners
+    // and the shape covers the same area as at target resolution
+    pub fn triangle(&mut self,
+                    t0   : Vec2i,
+                    t1   : Vec2i,
+                    t2   : Vec2i,
+                    color: [u8; 4],
+                    triangle_method: TriangleMethodEnum) {
+        let f = self.factor as f64;
+        let corner = |p: Vec2i| Vec2f::new(Scalar::new((p.x.value as f64 * f) as f32),
+                                           Scalar::new((p.y.value as f64 * f) as f32));
+        triangle_subpixel(corner(t0), corner(t1), corner(t2), color, &mut self.image, triangle_method);
+    }
+
+    // Separable resolve: horizontal pass into a float buffer, then vertical
+    pub fn resolve(&self, filter: ResolveFilterEnum) -> RgbaImage {
+        let f = self.factor as f32;
+        let wide = self.image.width();
+        let tall = self.image.height();
+
+        let horizontal = taps(self.width, wide, f, filter);
+        let mut rows = vec![[0.0f32; 4]; (self.width * tall) as usize];
+        for y in 0..tall {
+            for (x, taps) in horizontal.iter().enumerate() {
+                let mut sum = [0.0; 4];
+                for &(sx, w) in taps {
+                    let p = self.image.get_pixel(sx, y);
+                    for c in 0..4 {sum[c] += p[c] as f32 * w;}
+                }
+                rows[(y * self.width) as usize + x] = sum;
+            }
+        }
+
+        let vertical = taps(self.height, tall, f, filter);
+        RgbaImage::from_fn(self.width, self.height, |x, y| {
+            let mut sum = [0.0; 4];
+            for &(sy, w) in &vertical[y as usize] {
+                let p = rows[(sy * self.width + x) as usize];
+                for c in 0..4 {sum[c] += p[c] * w;}
+            }
+            Rgba(sum.map(|v| v.round().clamp(0.0, 255.0) as u8))
+        })
+    }
+}
+
+// Normalised (source index, weight) taps for every target pixel along an axis
+fn taps(target: u32, source: u32, factor: f32, filter: ResolveFilterEnum) -> Vec<Vec<(u32, f32)>> {
+    let reach = filter.radius() * factor;
+    (0..target).map(|t| {
+        let center = (t as f32 + 0.5) * factor;
+        let lo = (center - reach).floor().max(0.0) as u32;
+        let hi = ((center + reach).ceil() as u32).min(source);
+        let mut taps: Vec<(u32, f32)> = (lo..hi).map(|s| (s, filter.weight((s as f32 + 0.5 - center) / factor)))
+                                               .filter(|&(_, w)| w != 0.0)
+                                               .collect();
+        let total: f32 = taps.iter().map(|&(_, w)| w).sum();
+        if total.abs() > 1e-6 {
+            taps.iter_mut().for_each(|t| t.1 /= total);
+        }
+        taps
+    }).collect()
+}
@@ -0,0 +1,207 @@
+use std::f32::consts::PI;
+use image::RgbaImage;
+use crate::{line::ClipRect,
+            model::CoverageMask,
+            backend::{images::blend, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    STROKES
+------------------------------------------------------------------------------*/
+// Thick lines are tessellated into triangles and filled by the anti-aliased
+// triangle filler. Points are in continuous pixel coordinates, so a stroke
+// along y = 10.5 with width 1 fills exactly pixel row 10. All triangles of a
+// stroke share one coverage mask, so overlapping joins and caps are blended
+// only once and translucent strokes composite evenly.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineCapEnum {
+    BUTT,
+    ROUND,
+    SQUARE,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LineJoinEnum {
+    MITER,
+    ROUND,
+    BEVEL,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Stroke {
+    pub width      : f32,
+    pub cap        : LineCapEnum,
+    pub join       : LineJoinEnum,
+    // Longest allowed miter as a multiple of the width before it is beveled
+    pub miter_limit: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Stroke {
+        Stroke{width: 1.0, cap: LineCapEnum::BUTT, join: LineJoinEnum::MITER, miter_limit: 4.0}
+    }
+}
+
+fn vec2(x: f32, y: f32) -> Vec2f {
+    Vec2f::new(Scalar::new(x), Scalar::new(y))
+}
+
+fn length(v: Vec2f) -> f32 {
+    v.x.value.hypot(v.y.value)
+}
+
+impl Stroke {
+    pub fn new(width: f32, cap: LineCapEnum, join: LineJoinEnum) -> Stroke {
+        Stroke{width, cap, join, ..Stroke::default()}
+    }
+
+    // Triangles covering the stroke of the polyline through `points`, closed
+    // back to the first point if `closed`. Triangles may overlap.
+    pub fn triangles(&self, points: &[Vec2f], closed: bool) -> Vec<[Vec2f; 3]> {
+        let mut out = Vec::new();
+        let half = self.width * 0.5;
+        if half.is_nan() || half <= 0.0 {return out;}
+
+        let mut pts: Vec<Vec2f> = Vec::with_capacity(points.len());
+        for &p in points {
+            if pts.last().is_none_or(|&q| length(p - q) > 1e-6) {pts.push(p);}
+        }
+        if closed && pts.len() > 1 && length(pts[0] - pts[pts.len() - 1]) <= 1e-6 {pts.pop();}
+        let closed = closed && pts.len() > 2;
+
+        match pts.len() {
+            0 => return out,
+            // Zero-length stroke: only caps with extent of their own show up
+            1 => {
+                let p = pts[0];
+                match self.cap {
+                    LineCapEnum::BUTT   => {},
+                    LineCapEnum::ROUND  => disc(p, half, &mut out),
+                    LineCapEnum::SQUARE => quad(p, vec2(1.0, 0.0), -half, half, half, &mut out),
+                }
+                return out;
+            },
+            _ => {},
+        }
+
+        let n = pts.len();
+        let segments = if closed {n} else {n - 1};
+        for i in 0..segments {
+            let (a, b) = (pts[i], pts[(i + 1) % n]);
+            let d = (b - a) / Scalar::new(length(b - a));
+            quad(a, d, 0.0, length(b - a), half, &mut out);
+        }
+
+        let first = if closed {0} else {1};
+        let last  = if closed {n} else {n - 1};
+        for i in first..last {
+            let (prev, p, next) = (pts[(i + n - 1) % n], pts[i], pts[(i + 1) % n]);
+            self.join_triangles(prev, p, next, half, &mut out);
+        }
+
+        if !closed {
+            let d0 = (pts[1] - pts[0]) / Scalar::new(length(pts[1] - pts[0]));
+            let d1 = (pts[n - 2] - pts[n - 1]) / Scalar::new(length(pts[n - 2] - pts[n - 1]));
+            self.cap_triangles(pts[0], d0, half, &mut out);
+            self.cap_triangles(pts[n - 1], d1, half, &mut out);
+        }
+        out
+    }
+
+    // `d` points from the end into the stroke
+    fn cap_triangles(&self, p: Vec2f, d: Vec2f, half: f32, out: &mut Vec<[Vec2f; 3]>) {
+        match self.cap {
+            LineCapEnum::BUTT   => {},
+            LineCapEnum::ROUND  => disc(p, half, out),
+            LineCapEnum::SQUARE => quad(p, d, -half, 0.0, half, out),
+        }
+    }
+
+    fn join_triangles(&self, prev: Vec2f, p: Vec2f, next: Vec2f, half: f32, out: &mut Vec<[Vec2f; 3]>) {
+        let d0 = (p - prev) / Scalar::new(length(p - prev));
+        let d1 = (next - p) / Scalar::new(length(next - p));
+        let cross = d0.x.value * d1.y.value - d0.y.value * d1.x.value;
+        let dot   = d0.x.value * d1.x.value + d0.y.value * d1.y.value;
+        if cross.abs() < 1e-6 && dot > 0.0 {return;}
+
+        if self.join == LineJoinEnum::ROUND {
+            disc(p, half, out);
+            return;
+        }
+
+        // The outer side of the turn is opposite the direction it bends toward
+        let side = if cross > 0.0 {-half} else {half};
+        let n0 = vec2(-d0.y.value, d0.x.value) * Scalar::new(side);
+        let n1 = vec2(-d1.y.value, d1.x.value) * Scalar::new(side);
+        let (o0, o1) = (p + n0, p + n1);
+
+        if self.join == LineJoinEnum::MITER {
+            // 1 / cos of half the angle between the offsets is miter length / width
+            let cos_half = ((1.0 + dot) * 0.5).max(0.0).sqrt();
+            if cos_half > 0.0 && 1.0 / cos_half <= self.miter_limit {
+                let m = n0 + n1;
+                let tip = p + m * Scalar::new(half / (length(m) * cos_half));
+                out.push([p, o0, tip]);
+                out.push([p, tip, o1]);
+                return;
+            }
+        }
+        out.push([p, o0, o1]);
+    }
+}
+
+// Rectangle along unit direction `d` from `start` to `end` (distances from
+// `p`), `half` wide on each side
+fn quad(p: Vec2f, d: Vec2f, start: f32, end: f32, half: f32, out: &mut Vec<[Vec2f; 3]>) {
+    let n = vec2(-d.y.value, d.x.value) * Scalar::new(half);
+    let a = p + d * Scalar::new(start);
+    let b = p + d * Scalar::new(end);
+    out.push([a + n, b + n, b - n]);
+    out.push([a + n, b - n, a - n]);
+}
+
+// Triangle fan approximating a circle to within a tenth of a pixel
+fn disc(p: Vec2f, radius: f32, out: &mut Vec<[Vec2f; 3]>) {
+    let cos_step = (1.0 - 0.1 / radius).max(-1.0);
+    let segments = ((2.0 * PI / cos_step.acos()).ceil() as usize).clamp(8, 256);
+    let point = |i: usize| {
+        let angle = 2.0 * PI * i as f32 / segments as f32;
+        p + vec2(angle.cos(), angle.sin()) * Scalar::new(radius)
+    };
+    for i in 0..segments {
+        out.push([p, point(i), point(i + 1)]);
+    }
+}
+
+pub fn stroke_line(p0    : Vec2f,
+                   p1    : Vec2f,
+                   color : [u8; 4],
+                   stroke: &Stroke,
+                   image : &mut RgbaImage) {
+    stroke_polyline(&[p0, p1], false, color, stroke, image);
+}
+
+pub fn stroke_polyline(points: &[Vec2f],
+                       closed: bool,
+                       color : [u8; 4],
+                       stroke: &Stroke,
+                       image : &mut RgbaImage) {
+    let clip = ClipRect::from_image(image);
+    stroke_pixels(points, closed, stroke, clip, &mut |x, y, coverage| {
+        blend(image, x, y, color, coverage);
+    });
+}
+
+// Hands `plot` every pixel of the stroke inside `clip` exactly once
+pub(crate) fn stroke_pixels(points: &[Vec2f],
+                            closed: bool,
+                            stroke: &Stroke,
+                            clip  : ClipRect,
+                            plot  : &mut dyn FnMut(i32, i32, f32)) {
+    let triangles = stroke.triangles(points, closed);
+    let mut mask = CoverageMask::new(&triangles, clip);
+    for t in &triangles {
+        mask.triangle(t[0], t[1], t[2]);
+    }
+    mask.pixels(plot);
+}
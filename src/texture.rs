@@ -0,0 +1,270 @@
+use std::fmt;
+use image::{RgbaImage, Rgba};
+use crate::backend::geometry::*;
+
+/*------------------------------------------------------------------------------
+                    TEXTURES
+------------------------------------------------------------------------------*/
+// A texture owns its base image and (optionally) a box-filtered mip chain
+// down to 1x1. UV (0, 0) is the top-left corner of the base image and (1, 1)
+// the bottom-right, matching the row order of `RgbaImage`.
+#[derive(Debug, Clone)]
+pub struct Texture {
+    levels: Vec<RgbaImage>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextureError {
+    Empty{width: u32, height: u32},   // wrapping and filtering need at least one texel
+    CubeFaces,                        // cube map faces differ in size or are not square
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Empty{width, height} => write!(f, "texture image is empty ({}x{})", width, height),
+            TextureError::CubeFaces => write!(f, "cube map faces must be square and equally sized"),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl Texture {
+    pub fn new(image: RgbaImage) -> Result<Texture, TextureError> {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {return Err(TextureError::Empty{width, height});}
+        Ok(Texture{levels: vec![image]})
+    }
+
+    pub fn with_mipmaps(image: RgbaImage) -> Result<Texture, TextureError> {
+        let mut texture = Texture::new(image)?;
+        texture.generate_mipmaps();
+        Ok(texture)
+    }
+
+    pub fn generate_mipmaps(&mut self) {
+        self.levels.truncate(1);
+        loop {
+            let last = self.levels.last().unwrap();
+            let (w, h) = last.dimensions();
+            if w <= 1 && h <= 1 {break;}
+            let next = downsample(last);
+            self.levels.push(next);
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.levels[0].width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.levels[0].height()
+    }
+
+    pub fn levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn level(&self, level: usize) -> &RgbaImage {
+        &self.levels[level.min(self.levels.len() - 1)]
+    }
+}
+
+// 2x2 box filter; odd edges fold their last texel into the final output texel
+fn downsample(image: &RgbaImage) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    let (nw, nh) = ((w / 2).max(1), (h / 2).max(1));
+    RgbaImage::from_fn(nw, nh, |x, y| {
+        let xs = 2 * x..if x == nw - 1 {w} else {2 * x + 2};
+        let ys = 2 * y..if y == nh - 1 {h} else {2 * y + 2};
+        let mut sum = [0u32; 4];
+        let mut count = 0;
+        for sy in ys {
+            for sx in xs.clone() {
+                let p = image.get_pixel(sx, sy);
+                for c in 0..4 {sum[c] += p[c] as u32;}
+                count += 1;
+            }
+        }
+        Rgba(sum.map(|v| ((v + count / 2) / count) as u8))
+    })
+}
+
+/*------------------------------------------------------------------------------
+                    SAMPLERS
+------------------------------------------------------------------------------*/
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WrapEnum {
+    REPEAT,
+    CLAMP,
+    MIRROR,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FilterEnum {
+    NEAREST,
+    BILINEAR,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MipmapEnum {
+    NONE,       // always sample the base level
+    NEAREST,    // closest level
+    LINEAR,     // blend the two closest levels (trilinear with BILINEAR)
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sampler {
+    pub filter    : FilterEnum,
+    pub mipmap    : MipmapEnum,
+    pub wrap_u    : WrapEnum,
+    pub wrap_v    : WrapEnum,
+    pub anisotropy: u32,     // maximum probes along the footprint's major axis, 1 disables
+}
+
+impl Default for Sampler {
+    fn default() -> Self {
+        Sampler{filter    : FilterEnum::BILINEAR,
+                mipmap    : MipmapEnum::LINEAR,
+                wrap_u    : WrapEnum::REPEAT,
+                wrap_v    : WrapEnum::REPEAT,
+                anisotropy: 1}
+    }
+}
+
+fn wrap(i: i32, size: u32, mode: WrapEnum) -> u32 {
+    let n = size as i32;
+    (match mode {
+        WrapEnum::REPEAT => i.rem_euclid(n),
+        WrapEnum::CLAMP  => i.clamp(0, n - 1),
+        WrapEnum::MIRROR => {
+            let m = i.rem_euclid(2 * n);
+            if m >= n {2 * n - 1 - m} else {m}
+        }
+    }) as u32
+}
+
+impl Sampler {
+    pub fn new(filter: FilterEnum, wrap: WrapEnum) -> Sampler {
+        Sampler{filter, wrap_u: wrap, wrap_v: wrap, ..Sampler::default()}
+    }
+
+    fn texel(&self, image: &RgbaImage, x: i32, y: i32) -> [f32; 4] {
+        let p = image.get_pixel(wrap(x, image.width(), self.wrap_u), wrap(y, image.height(), self.wrap_v));
+        p.0.map(|v| v as f32)
+    }
+
+    // Filtered lookup in one mip level
+    fn sample_level(&self, texture: &Texture, level: usize, u: f32, v: f32) -> [f32; 4] {
+        let image = texture.level(level);
+        let x = u * image.width() as f32;
+        let y = v * image.height() as f32;
+        match self.filter {
+            FilterEnum::NEAREST => self.texel(image, x.floor() as i32, y.floor() as i32),
+            FilterEnum::BILINEAR => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+                let a = self.texel(image, x0,     y0);
+                let b = self.texel(image, x0 + 1, y0);
+                let c = self.texel(image, x0,     y0 + 1);
+                let d = self.texel(image, x0 + 1, y0 + 1);
+                let mut out = [0.0; 4];
+                for i in 0..4 {
+                    let top    = a[i] + (b[i] - a[i]) * fx;
+                    let bottom = c[i] + (d[i] - c[i]) * fx;
+                    out[i] = top + (bottom - top) * fy;
+                }
+                out
+            }
+        }
+    }
+
+    fn sample_lod(&self, texture: &Texture, u: f32, v: f32, lod: f32) -> [f32; 4] {
+        let top = (texture.levels() - 1) as f32;
+        match self.mipmap {
+            MipmapEnum::NONE    => self.sample_level(texture, 0, u, v),
+            MipmapEnum::NEAREST => self.sample_level(texture, lod.round().clamp(0.0, top) as usize, u, v),
+            MipmapEnum::LINEAR  => {
+                let lod = lod.clamp(0.0, top);
+                let (lo, t) = (lod.floor(), lod - lod.floor());
+                let a = self.sample_level(texture, lo as usize, u, v);
+                if t == 0.0 {return a;}
+                let b = self.sample_level(texture, lo as usize + 1, u, v);
+                [0, 1, 2, 3].map(|i| a[i] + (b[i] - a[i]) * t)
+            }
+        }
+    }
+
+    // Magnified / base level lookup, for callers without derivatives
+    pub fn sample(&self, texture: &Texture, uv: Vec2f) -> [u8; 4] {
+        to_u8(self.sample_lod(texture, uv.x.value, uv.y.value, 0.0))
+    }
+
+    // `duv_dx` and `duv_dy` are the change in UV per screen pixel along x and
+    // y; they pick the mip level and, with anisotropy, the probe direction.
+    pub fn sample_grad(&self,
+                       texture: &Texture,
+                       uv     : Vec2f,
+                       duv_dx : Vec2f,
+                       duv_dy : Vec2f)
+        -> [u8; 4] {
+        let (w, h) = (texture.width() as f32, texture.height() as f32);
+        let dx = (duv_dx.x.value * w, duv_dx.y.value * h);
+        let dy = (duv_dy.x.value * w, duv_dy.y.value * h);
+        let len_x = (dx.0 * dx.0 + dx.1 * dx.1).sqrt();
+        let len_y = (dy.0 * dy.0 + dy.1 * dy.1).sqrt();
+        let (major, minor, axis) = if len_x >= len_y {(len_x, len_y, duv_dx)} else {(len_y, len_x, duv_dy)};
+
+        let probes = if self.anisotropy > 1 && minor > 0.0 {
+            (major / minor).ceil().clamp(1.0, self.anisotropy as f32) as u32
+        } else {1};
+        if probes == 1 {
+            return to_u8(self.sample_lod(texture, uv.x.value, uv.y.value, major.max(1e-8).log2()));
+        }
+
+        // Probes spread evenly along the major axis, each filtered for the
+        // footprint width left after dividing the axis between them
+        let lod = (major / probes as f32).max(1e-8).log2();
+        let mut sum = [0.0; 4];
+        for i in 0..probes {
+            let t = (i as f32 + 0.5) / probes as f32 - 0.5;
+            let s = self.sample_lod(texture,
+                                    uv.x.value + axis.x.value * t,
+                                    uv.y.value + axis.y.value * t,
+                                    lod);
+            for c in 0..4 {sum[c] += s[c] / probes as f32;}
+        }
+        to_u8(sum)
+    }
+}
+
+fn to_u8(c: [f32; 4]) -> [u8; 4] {
+    c.map(|v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+// Screen-space UV derivatives of a triangle. Barycentrics are affine in screen
+// space, so the rasterizer can compute these once per triangle.
+pub fn uv_derivatives(p0 : Vec2f,
+                      p1 : Vec2f,
+                      p2 : Vec2f,
+                      uv0: Vec2f,
+                      uv1: Vec2f,
+                      uv2: Vec2f)
+    -> (Vec2f, Vec2f) {
+    let e1 = p1 - p0;
+    let e2 = p2 - p0;
+    let t1 = uv1 - uv0;
+    let t2 = uv2 - uv0;
+    let det = e1.x.value * e2.y.value - e2.x.value * e1.y.value;
+    if det == 0.0 {
+        let zero = Vec2f::new(Scalar::new(0.0), Scalar::new(0.0));
+        return (zero, zero);
+    }
+    let inv = Scalar::new(1.0 / det);
+    let duv_dx = (t1 * Scalar::new(e2.y.value) - t2 * Scalar::new(e1.y.value)) * inv;
+    let duv_dy = (t2 * Scalar::new(e1.x.value) - t1 * Scalar::new(e2.x.value)) * inv;
+    (duv_dx, duv_dy)
+}
@@ -0,0 +1,177 @@
+use std::{sync::{atomic::{AtomicUsize, Ordering}, Mutex}, thread};
+use image::RgbaImage;
+use crate::{line::{line_pixels, ClipRect, DrawBehavior},
+            model::{triangle_pixels, TriangleMethodEnum},
+            backend::{images::set_coverage, geometry::*}};
+
+/*------------------------------------------------------------------------------
+                    TILE-BASED (BINNING) RASTERIZER
+------------------------------------------------------------------------------*/
+// Primitives are recorded first, binned into the tiles their bounding box
+// overlaps, and each tile then replays its bin in submission order on its own
+// thread. Tiles never share pixels, so the result is identical to drawing the
+// same primitives one after another with `line` and `triangle`.
+pub const TILE_SIZE: u32 = 64;
+
+#[derive(Copy, Clone)]
+pub enum Primitive<'a> {
+    Line{p0: Vec2i, p1: Vec2i, color: [u8; 4], method: &'a dyn DrawBehavior},
+    Triangle{t0: Vec2i, t1: Vec2i, t2: Vec2i, color: [u8; 4], method: TriangleMethodEnum},
+}
+
+impl Primitive<'_> {
+    // Inclusive pixel bounds (min x, min y, max x, max y). Lines get a one
+    // pixel margin for the neighbour pixels anti-aliased methods touch.
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        let (points, margin): ([Vec2i; 3], i32) = match *self {
+            Primitive::Line{p0, p1, ..}         => ([p0, p1, p1], 1),
+            Primitive::Triangle{t0, t1, t2, ..} => ([t0, t1, t2], 0),
+        };
+        let b = points.iter().fold((i32::MAX, i32::MAX, i32::MIN, i32::MIN), |b, p| {
+            (b.0.min(p.x.value), b.1.min(p.y.value),
+             b.2.max(p.x.value), b.3.max(p.y.value))
+        });
+        (b.0.saturating_sub(margin), b.1.saturating_sub(margin), b.2.saturating_add(margin), b.3.saturating_add(margin))
+    }
+
+    fn color(&self) -> [u8; 4] {
+        match self {
+            Primitive::Line{color, ..}     => *color,
+            Primitive::Triangle{color, ..} => *color,
+        }
+    }
+
+    // The line and triangle methods skip whatever cannot reach `clip`, so a
+    // tile only walks its own span of a primitive.
+    fn rasterize(&self, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32, f32)) {
+        match *self {
+            Primitive::Line{p0, p1, method, ..} => line_pixels(method, p0, p1, clip, plot),
+            Primitive::Triangle{t0, t1, t2, method, ..} => triangle_pixels(t0, t1, t2, method, clip, &mut |x, y| plot(x, y, 1.0)),
+        }
+    }
+}
+
+pub struct TileRenderer<'a> {
+    tile_size : u32,
+    threads   : usize,
+    primitives: Vec<Primitive<'a>>,
+}
+
+impl Default for TileRenderer<'_> {
+    fn default() -> Self {
+        TileRenderer::new(TILE_SIZE)
+    }
+}
+
+impl<'a> TileRenderer<'a> {
+    pub fn new(tile_size: u32) -> TileRenderer<'a> {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        TileRenderer{tile_size: tile_size.max(1), threads, primitives: Vec::new()}
+    }
+
+    pub fn with_threads(mut self, threads: usize) -> TileRenderer<'a> {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn push(&mut self, primitive: Primitive<'a>) {
+        self.primitives.push(primitive);
+    }
+
+    pub fn line(&mut self,
+                p0   : Vec2i,
+                p1   : Vec2i,
+                color: [u8; 4],
+                method: impl Into<&'a dyn DrawBehavior>) {
+        self.push(Primitive::Line{p0, p1, color, method: method.into()});
+    }
+
+    pub fn triangle(&mut self,
+                    t0   : Vec2i,
+                    t1   : Vec2i,
+                    t2   : Vec2i,
+                    color: [u8; 4],
+                    method: TriangleMethodEnum) {
+        self.push(Primitive::Triangle{t0, t1, t2, color, method});
+    }
+
+    pub fn clear(&mut self) {
+        self.primitives.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.primitives.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primitives.is_empty()
+    }
+
+    // Sort primitive indices into the tiles they overlap, keeping submission order
+    fn bin(&self, tiles_x: u32, tiles_y: u32, width: u32, height: u32) -> Vec<Vec<usize>> {
+        let mut bins = vec![Vec::new(); (tiles_x * tiles_y) as usize];
+        let size = self.tile_size as i32;
+        for (index, primitive) in self.primitives.iter().enumerate() {
+            let (x0, y0, x1, y1) = primitive.bounds();
+            if x1 < 0 || y1 < 0 || x0 >= width as i32 || y0 >= height as i32 {continue;}
+            let tx0 = x0.max(0) / size;
+            let ty0 = y0.max(0) / size;
+            let tx1 = x1.min(width as i32 - 1) / size;
+            let ty1 = y1.min(height as i32 - 1) / size;
+            for ty in ty0..=ty1 {
+                for tx in tx0..=tx1 {
+                    bins[(ty as u32 * tiles_x + tx as u32) as usize].push(index);
+                }
+            }
+        }
+        bins
+    }
+
+    pub fn render(&self, image: &mut RgbaImage) {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {return;}
+        let size    = self.tile_size;
+        let tiles_x = width.div_ceil(size);
+        let tiles_y = height.div_ceil(size);
+        let bins    = self.bin(tiles_x, tiles_y, width, height);
+
+        let next    = AtomicUsize::new(0);
+        let done    = Mutex::new(Vec::with_capacity(bins.len()));
+        let source  = &*image;
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(bins.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= bins.len() {break;}
+                    if bins[index].is_empty() {continue;}
+
+                    let ox = (index as u32 % tiles_x) * size;
+                    let oy = (index as u32 / tiles_x) * size;
+                    let tw = size.min(width  - ox);
+                    let th = size.min(height - oy);
+                    let mut tile = RgbaImage::from_fn(tw, th, |x, y| *source.get_pixel(ox + x, oy + y));
+                    let clip = ClipRect::new(ox as i32, oy as i32, (ox + tw) as i32, (oy + th) as i32);
+
+                    for &primitive in &bins[index] {
+                        let primitive = &self.primitives[primitive];
+                        let color = primitive.color();
+                        primitive.rasterize(&clip, &mut |x, y, coverage| {
+                            let (lx, ly) = (x - ox as i32, y - oy as i32);
+                            if lx >= 0 && ly >= 0 && lx < tw as i32 && ly < th as i32 {
+                                set_coverage(&mut tile, lx, ly, color, coverage);
+                            }
+                        });
+                    }
+                    done.lock().unwrap().push((ox, oy, tile));
+                });
+            }
+        });
+
+        for (ox, oy, tile) in done.into_inner().unwrap() {
+            for (x, y, pixel) in tile.enumerate_pixels() {
+                image.put_pixel(ox + x, oy + y, *pixel);
+            }
+        }
+    }
+}
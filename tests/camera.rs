@@ -0,0 +1,25 @@
+mod common;
+
+use render_with_rust::{
+    camera::Camera
+};
+use common::vec3;
+
+#[test]
+fn projects_through_the_view() {
+    let camera = Camera::look_at(vec3(0.0, 0.0, 5.0), vec3(0.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0));
+
+    let center = camera.project(vec3(0.0, 0.0, 0.0), 200, 100).unwrap();
+    assert!((center.x.value - 100.0).abs() < 1e-4 && (center.y.value - 50.0).abs() < 1e-4);
+
+    // +x is to the right, +y is up (smaller image y)
+    let right = camera.project(vec3(1.0, 0.0, 0.0), 200, 100).unwrap();
+    let up    = camera.project(vec3(0.0, 1.0, 0.0), 200, 100).unwrap();
+    assert!(right.x.value > 100.0 && up.y.value < 50.0);
+
+    // Depth runs from 0 at the near plane to 1 at the far plane
+    let near = camera.project_view(vec3(0.0, 0.0, camera.near), 200, 100);
+    let far  = camera.project_view(vec3(0.0, 0.0, camera.far), 200, 100);
+    assert!(near.z.value.abs() < 1e-5 && (far.z.value - 1.0).abs() < 1e-5);
+    assert!(camera.project(vec3(0.0, 0.0, 6.0), 200, 100).is_none());
+}
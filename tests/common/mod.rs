@@ -0,0 +1,44 @@
+// Fixtures shared by the integration tests; each test crate uses a subset.
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use image::RgbaImage;
+use render_with_rust::backend::{images::set_all, geometry::*};
+
+pub fn point(x: i32, y: i32) -> Vec2i {
+    Vec2i::new(Scalar::new(x), Scalar::new(y))
+}
+
+pub fn pt(x: f32, y: f32) -> Vec2f {
+    Vec2f::new(Scalar::new(x), Scalar::new(y))
+}
+
+pub fn vec3(x: f32, y: f32, z: f32) -> Vec3f {
+    Vec3f::new(Scalar::new(x), Scalar::new(y), Scalar::new(z))
+}
+
+// Square black canvas
+pub fn canvas(size: u32) -> RgbaImage {
+    let mut img = RgbaImage::new(size, size);
+    set_all(&mut img, [0, 0, 0, 255]);
+    img
+}
+
+// Pixels with any red in them
+pub fn lit(img: &RgbaImage) -> HashSet<(i32, i32)> {
+    img.enumerate_pixels().filter(|(_, _, p)| p[0] > 0).map(|(x, y, _)| (x as i32, y as i32)).collect()
+}
+
+// Small LCG so randomized scenes are the same on every run
+pub struct Lcg(u32);
+
+impl Lcg {
+    pub fn new(seed: u32) -> Lcg {
+        Lcg(seed)
+    }
+
+    pub fn next(&mut self, bound: u32) -> i32 {
+        self.0 = self.0.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        ((self.0 >> 8) % bound) as i32
+    }
+}
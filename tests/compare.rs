@@ -0,0 +1,44 @@
+mod common;
+
+use image::{RgbaImage, imageops};
+use render_with_rust::{
+    line::{line, LineMethodEnum::*},
+    backend::{images::{set, set_all}, compare::*}
+};
+use common::point;
+
+#[test]
+fn bresenham_matches_reference() {
+    let mut img = RgbaImage::new(100, 100);
+    set_all(&mut img, [0, 0, 0, 255]);
+    set(&mut img, 52, 41, [0, 255, 0, 255]);
+    line(point(13, 20), point(80, 40), [  0, 0, 0,   0], &mut img, BRESENHAM);
+    line(point(20, 13), point(40, 80), [255, 0, 0, 255], &mut img, BRESENHAM);
+    line(point(80, 40), point(13, 20), [255, 0, 0, 255], &mut img, BRESENHAM);
+    imageops::flip_vertical_in_place(&mut img);
+
+    assert_matches_reference(&img, "out/lineBresenham.png", 0);
+}
+
+#[test]
+fn report_metrics() {
+    let mut reference = RgbaImage::new(16, 16);
+    set_all(&mut reference, [40, 80, 120, 255]);
+
+    let same = compare(&reference, &reference, 0).unwrap();
+    assert!(same.passed());
+    assert_eq!(same.max_error, 0);
+    assert!(same.psnr.is_infinite());
+    assert!((same.ssim - 1.0).abs() < 1e-9);
+
+    let mut actual = reference.clone();
+    set(&mut actual, 3, 4, [40, 80, 130, 255]);
+    set(&mut actual, 9, 9, [40, 81, 120, 255]);
+    let report = compare(&actual, &reference, 2).unwrap();
+    assert_eq!(report.mismatched, 1);
+    assert_eq!(report.max_error, 10);
+    assert!(report.psnr.is_finite() && report.ssim < 1.0);
+    assert!(compare(&actual, &reference, 10).unwrap().passed());
+
+    assert!(compare(&actual, &RgbaImage::new(8, 8), 0).is_err());
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use image::{Rgba, RgbaImage};
+use render_with_rust::{
+    backend::images::set_all,
+    texture::{Sampler, FilterEnum, TextureError, WrapEnum},
+    envmap::*
+};
+use common::vec3;
+
+fn face_color(face: CubeFaceEnum) -> [u8; 4] {
+    let i = CUBE_FACES.iter().position(|f| *f == face).unwrap() as u8;
+    [i * 40, 255 - i * 40, 100, 255]
+}
+
+#[test]
+fn face_lookup_round_trips() {
+    for face in CUBE_FACES {
+        for &(u, v) in &[(0.1, 0.2), (0.5, 0.5), (0.9, 0.7)] {
+            let (hit, hu, hv) = CubeFaceEnum::from_direction(face.direction(u, v));
+            assert_eq!(hit, face);
+            assert!((hu - u).abs() < 1e-5 && (hv - v).abs() < 1e-5);
+        }
+    }
+}
+
+#[test]
+fn rendered_faces_map_to_directions() {
+    let sampler = Sampler::new(FilterEnum::NEAREST, WrapEnum::CLAMP);
+    let cube = CubeMap::render(8, &mut |face, image| {set_all(image, face_color(face));}).unwrap();
+
+    assert_eq!(cube.sample(&sampler, vec3(1.0, 0.1, 0.0)), face_color(CubeFaceEnum::POSX));
+    assert_eq!(cube.sample(&sampler, vec3(0.0, -2.0, 0.3)), face_color(CubeFaceEnum::NEGY));
+    assert_eq!(cube.sample(&sampler, vec3(0.2, 0.0, -5.0)), face_color(CubeFaceEnum::NEGZ));
+    // Looking straight down +Z reflects off a mirror facing back along -Z
+    assert_eq!(cube.reflect(&sampler, vec3(0.0, 0.0, 1.0), vec3(0.0, 0.0, -1.0)),
+               face_color(CubeFaceEnum::NEGZ));
+
+    // Unwrap to a panorama and back: every face keeps its color at its center
+    let panorama = cube.to_equirectangular(128, 64, &sampler);
+    assert_eq!(panorama.get_pixel(64, 1).0, face_color(CubeFaceEnum::POSY));
+    assert_eq!(panorama.get_pixel(64, 32).0, face_color(CubeFaceEnum::POSZ));
+    let back = CubeMap::from_equirectangular(&panorama, 8, &sampler).unwrap();
+    for face in CUBE_FACES {
+        assert_eq!(*back.face(face).get_pixel(4, 4), Rgba(face_color(face)));
+    }
+}
+
+#[test]
+fn empty_or_mismatched_faces_are_errors() {
+    let sampler = Sampler::new(FilterEnum::NEAREST, WrapEnum::CLAMP);
+    let panorama = RgbaImage::new(16, 8);
+    assert_eq!(CubeMap::from_equirectangular(&panorama, 0, &sampler).err(), Some(TextureError::Empty{width: 0, height: 0}));
+    assert!(CubeMap::render(0, &mut |_, _| {}).is_err());
+    assert!(render_equirectangular(0, 16, 8, &sampler, &mut |_, _| {}).is_err());
+
+    let mut faces = CUBE_FACES.map(|_| RgbaImage::new(4, 4));
+    faces[3] = RgbaImage::new(4, 5);
+    assert_eq!(CubeMap::new(faces).err(), Some(TextureError::CubeFaces));
+}
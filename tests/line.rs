@@ -0,0 +1,503 @@
+mod common;
+
+use image::{RgbaImage, Rgba};
+use render_with_rust::{
+    camera::Camera,
+    line::{line, lines, line_clipped, line_3d, line_patterned, polyline, line_gradient, line_gradient_depth,
+           ClipRect, DrawBehavior, Naive0, Bresenham, LinePatternEnum, LineVertex,
+           GradientModeEnum, LineMethodEnum::*},
+    ssaa::{Supersampler, ResolveFilterEnum},
+    tiles::TileRenderer,
+    backend::{images::DepthBuffer, geometry::*}
+};
+use common::{point, vec3, canvas, Lcg};
+
+#[test]
+fn wu_spreads_coverage_across_minor_axis() {
+    let mut img = canvas(40);
+    line(point(2, 5), point(32, 15), [255, 255, 255, 255], &mut img, WU);
+
+    // Interior columns carry one pixel's worth of intensity split over two rows
+    for x in 4..30 {
+        let column: u32 = (0..40).map(|y| img.get_pixel(x, y)[0] as u32).sum();
+        assert!((column as i32 - 255).abs() <= 2, "column {} sums to {}", x, column);
+    }
+    // Endpoints are half covered
+    assert_eq!(img.get_pixel(2, 5)[0], 128);
+    assert!(img.pixels().any(|p| p[0] > 0 && p[0] < 255));
+}
+
+#[test]
+fn wu_blends_rather_than_overwrites() {
+    let mut img = canvas(40);
+    line(point(0, 10), point(39, 20), [255, 0, 0, 255], &mut img, WU);
+    line(point(0, 20), point(39, 10), [0, 0, 255, 255], &mut img, WU);
+    // Where the lines cross, both colors survive in the partially covered pixels
+    assert!(img.pixels().any(|p| p[0] > 0 && p[2] > 0));
+}
+
+#[test]
+fn wu_steep_lines_mirror_shallow_lines() {
+    let mut shallow = canvas(40);
+    let mut steep   = canvas(40);
+    line(point(3, 7), point(35, 19), [255, 255, 255, 255], &mut shallow, WU);
+    line(point(7, 3), point(19, 35), [255, 255, 255, 255], &mut steep, WU);
+    for (x, y, p) in shallow.enumerate_pixels() {
+        assert_eq!(p, steep.get_pixel(y, x));
+    }
+}
+
+#[test]
+fn clipping_keeps_visible_pixels_identical() {
+    let clip = ClipRect::new(50, 40, 130, 110);
+    let mut rng = Lcg::new(11);
+    let mut next = |bound: u32| rng.next(bound);
+
+    for method in [NAIVE0, NAIVE1, NAIVE2, BRESENHAM, WU, DDA, EFLA, DOUBLESTEP, RUNSLICE] {
+        for _ in 0..50 {
+            let p0 = point(next(200), next(160));
+            let p1 = point(next(200), next(160));
+
+            let mut full = RgbaImage::new(200, 160);
+            let mut clipped = RgbaImage::new(200, 160);
+            line(p0, p1, [255, 255, 255, 255], &mut full, method);
+            line_clipped(p0, p1, [255, 255, 255, 255], &mut clipped, clip, method);
+
+            for (x, y, p) in clipped.enumerate_pixels() {
+                let expected = if clip.contains(x as i32, y as i32) {*full.get_pixel(x, y)} else {Rgba([0; 4])};
+                assert_eq!(*p, expected, "{:?} {:?} -> {:?} at ({}, {})", method, p0, p1, x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn off_image_lines_are_clipped() {
+    let mut img = canvas(40);
+    // Far outside the image on both ends: must neither panic nor walk every step
+    line(point(-100_000_000, -50_000_000), point(100_000_000, 50_000_000), [255, 0, 0, 255], &mut img, BRESENHAM);
+    line(point(-100_000_000, 20), point(100_000_000, 20), [255, 0, 0, 255], &mut img, WU);
+    line(point(39, -5), point(39, 45), [255, 0, 0, 255], &mut img, NAIVE1);
+    assert_eq!(img.get_pixel(20, 10)[0], 255);
+    assert_eq!(img.get_pixel(5, 20)[0], 255);
+    assert_eq!(img.get_pixel(39, 0)[0], 255);
+
+    let visible = ClipRect::from_image(&img);
+    let segment = visible.clip_segment(Vec2f::new(Scalar::new(-10.0), Scalar::new(20.0)),
+                                       Vec2f::new(Scalar::new(50.0), Scalar::new(20.0)));
+    let (a, b) = segment.unwrap();
+    assert_eq!((a.x.value, b.x.value), (0.0, 39.0));
+    assert!(visible.clip_segment(Vec2f::new(Scalar::new(-10.0), Scalar::new(-1.0)),
+                                 Vec2f::new(Scalar::new(50.0), Scalar::new(-1.0))).is_none());
+}
+
+fn lit(img: &RgbaImage, x: u32, y: u32) -> bool {
+    img.get_pixel(x, y)[0] > 0
+}
+
+#[test]
+fn dashes_follow_lengths_and_phase() {
+    let white = [255, 255, 255, 255];
+    let mut plain   = canvas(40);
+    let mut shifted = canvas(40);
+    let mut odd     = canvas(40);
+    line_patterned(point(0, 5), point(30, 5), white, &mut plain, BRESENHAM, &LinePatternEnum::dash(&[4.0, 2.0], 0.0));
+    line_patterned(point(0, 5), point(30, 5), white, &mut shifted, BRESENHAM, &LinePatternEnum::dash(&[4.0, 2.0], 2.0));
+    line_patterned(point(0, 5), point(30, 5), white, &mut odd, BRESENHAM, &LinePatternEnum::dash(&[3.0], 0.0));
+
+    // Bresenham leaves the end pixel to the next segment
+    for x in 0..30 {
+        assert_eq!(lit(&plain, x, 5), x % 6 < 4, "x = {}", x);
+        assert_eq!(lit(&shifted, x, 5), (x + 2) % 6 < 4, "x = {}", x);
+        assert_eq!(lit(&odd, x, 5), x % 6 < 3, "x = {}", x);
+    }
+}
+
+#[test]
+fn stipple_bits_repeat_per_pixel_step() {
+    let white = [255, 255, 255, 255];
+    let mut single = canvas(40);
+    let mut double = canvas(40);
+    line_patterned(point(3, 0), point(3, 39), white, &mut single, BRESENHAM, &LinePatternEnum::stipple(0x0F0F, 1));
+    line_patterned(point(3, 0), point(3, 39), white, &mut double, BRESENHAM, &LinePatternEnum::stipple(0x0F0F, 2));
+
+    for y in 0..39 {
+        assert_eq!(lit(&single, 3, y), y % 8 < 4, "y = {}", y);
+        assert_eq!(lit(&double, 3, y), (y / 2) % 8 < 4, "y = {}", y);
+    }
+}
+
+#[test]
+fn pattern_continues_across_polyline_segments() {
+    let mut img = canvas(40);
+    let corner = [point(0, 5), point(7, 5), point(7, 30)];
+    polyline(&corner, [255, 255, 255, 255], &mut img, BRESENHAM, &LinePatternEnum::dash(&[4.0, 2.0], 0.0));
+
+    for x in 0..=7 {
+        assert_eq!(lit(&img, x, 5), x % 6 < 4, "x = {}", x);
+    }
+    for y in 6..30 {
+        assert_eq!(lit(&img, 7, y), (7 + y - 5) % 6 < 4, "y = {}", y);
+    }
+
+    // A solid polyline still reaches every vertex
+    let mut solid = canvas(40);
+    polyline(&corner, [255, 255, 255, 255], &mut solid, WU, &LinePatternEnum::SOLID);
+    assert!(lit(&solid, 0, 5) && lit(&solid, 7, 5) && lit(&solid, 7, 30));
+}
+
+#[test]
+fn gradients_interpolate_in_both_modes() {
+    let (black, white) = ([0, 0, 0, 255], [255, 255, 255, 255]);
+    let v0 = LineVertex::new(point(0, 5), black);
+    let v1 = LineVertex::new(point(30, 5), white);
+    let mut linear = canvas(40);
+    let mut srgb   = canvas(40);
+    line_gradient(v0, v1, &mut linear, BRESENHAM, GradientModeEnum::LINEAR);
+    line_gradient(v0, v1, &mut srgb, BRESENHAM, GradientModeEnum::SRGB);
+
+    assert_eq!(linear.get_pixel(0, 5)[0], 0);
+    assert_eq!(linear.get_pixel(15, 5)[0], 128);
+    assert_eq!(linear.get_pixel(29, 5)[0], 247);
+    // Half of white in linear light encodes to about 0.735 in sRGB
+    assert_eq!(srgb.get_pixel(15, 5)[0], 188);
+    assert!((1..30).all(|x| srgb.get_pixel(x, 5)[0] >= linear.get_pixel(x, 5)[0]));
+
+    // Reversing the endpoints mirrors the ramp, steep lines run along y
+    let mut steep = canvas(40);
+    line_gradient(LineVertex::new(point(3, 30), black), LineVertex::new(point(5, 0), white),
+                  &mut steep, WU, GradientModeEnum::LINEAR);
+    let column = |y: u32| (0..40).map(|x| steep.get_pixel(x, y)[1] as u32).max().unwrap();
+    assert!(column(28) < column(15) && column(15) < column(2));
+}
+
+#[test]
+fn gradient_depth_keeps_nearest() {
+    let mut img   = canvas(40);
+    let mut depth = DepthBuffer::from_pixel(40, 40, image::Luma([f32::INFINITY]));
+    let red  = [255, 0, 0, 255];
+    let blue = [0, 0, 255, 255];
+
+    // Near red line at depth 1, then a blue one crossing it from depth 0 to 2
+    line_gradient_depth(LineVertex::new(point(20, 0), red).with_depth(1.0),
+                        LineVertex::new(point(20, 39), red).with_depth(1.0),
+                        &mut img, &mut depth, BRESENHAM, GradientModeEnum::LINEAR);
+    line_gradient_depth(LineVertex::new(point(0, 10), blue).with_depth(0.0),
+                        LineVertex::new(point(39, 10), blue).with_depth(2.0),
+                        &mut img, &mut depth, BRESENHAM, GradientModeEnum::LINEAR);
+    line_gradient_depth(LineVertex::new(point(0, 30), blue).with_depth(2.0),
+                        LineVertex::new(point(39, 30), blue).with_depth(0.0),
+                        &mut img, &mut depth, BRESENHAM, GradientModeEnum::LINEAR);
+
+    // At x = 20 the first blue line is just past depth 1, the second in front
+    assert_eq!(*img.get_pixel(20, 10), Rgba(red));
+    assert_eq!(*img.get_pixel(20, 30), Rgba(blue));
+    assert!((depth.get_pixel(20, 30)[0] - 19.0 / 39.0 * 2.0).abs() < 1e-5);
+}
+
+// Plots only the two endpoints
+struct Endpoints;
+
+impl DrawBehavior for Endpoints {
+    fn draw(&self, p0: Vec2i, p1: Vec2i, _clip: &ClipRect, plot: &mut dyn FnMut(i32, i32, f32)) {
+        plot(p0.x.value, p0.y.value, 1.0);
+        plot(p1.x.value, p1.y.value, 1.0);
+    }
+}
+
+// Wraps a built-in method and doubles every pixel downwards
+struct Doubled;
+
+impl DrawBehavior for Doubled {
+    fn draw(&self, p0: Vec2i, p1: Vec2i, clip: &ClipRect, plot: &mut dyn FnMut(i32, i32, f32)) {
+        Bresenham.draw(p0, p1, clip, &mut |x, y, coverage| {
+            plot(x, y, coverage);
+            plot(x, y + 1, coverage);
+        });
+    }
+}
+
+#[test]
+fn custom_methods_draw_through_line() {
+    let mut img = canvas(40);
+    line(point(3, 4), point(30, 20), [255, 255, 255, 255], &mut img, &Endpoints);
+    let lit: Vec<(u32, u32)> = img.enumerate_pixels().filter(|(_, _, p)| p[0] > 0).map(|(x, y, _)| (x, y)).collect();
+    assert_eq!(lit, vec![(3, 4), (30, 20)]);
+
+    // Pixels pushed off the image by a custom method are still clipped
+    let mut reference = canvas(40);
+    let mut img = canvas(40);
+    line(point(0, 39), point(39, 39), [255, 255, 255, 255], &mut img, &Doubled);
+    line(point(0, 39), point(39, 39), [255, 255, 255, 255], &mut reference, BRESENHAM);
+    assert!(img == reference);
+
+    // Built-ins resolve to the same rasterizer the enum uses
+    let mut resolved = canvas(40);
+    line(point(0, 39), point(39, 39), [255, 255, 255, 255], &mut resolved, BRESENHAM.behavior());
+    assert!(resolved == reference);
+}
+
+#[test]
+fn custom_methods_draw_through_every_entry_point() {
+    let white = [255, 255, 255, 255];
+    let lit = |img: &RgbaImage| -> Vec<(u32, u32)> {
+        img.enumerate_pixels().filter(|(_, _, p)| p[0] > 0).map(|(x, y, _)| (x, y)).collect()
+    };
+    let (a, b, c) = (point(3, 4), point(30, 20), point(10, 35));
+
+    let mut img = canvas(40);
+    line_clipped(a, b, white, &mut img, ClipRect::new(0, 0, 20, 20), &Endpoints);
+    assert_eq!(lit(&img), vec![(3, 4)]);
+
+    let mut img = canvas(40);
+    polyline(&[a, b, c], white, &mut img, &Endpoints, &LinePatternEnum::SOLID);
+    assert_eq!(lit(&img), vec![(3, 4), (30, 20), (10, 35)]);
+
+    let mut img = canvas(40);
+    line_patterned(a, b, white, &mut img, &Endpoints, &LinePatternEnum::SOLID);
+    assert_eq!(lit(&img), vec![(3, 4), (30, 20)]);
+
+    let mut img = canvas(40);
+    line_gradient(LineVertex::new(a, white), LineVertex::new(b, white), &mut img, &Endpoints, GradientModeEnum::LINEAR);
+    assert_eq!(lit(&img), vec![(3, 4), (30, 20)]);
+
+    let mut img = canvas(40);
+    let mut tiles = TileRenderer::new(16);
+    tiles.line(a, b, white, &Endpoints);
+    tiles.render(&mut img);
+    assert_eq!(lit(&img), vec![(3, 4), (30, 20)]);
+
+    let mut ssaa = Supersampler::new(40, 40, 1);
+    ssaa.line(a, b, white, &Endpoints);
+    assert_eq!(lit(&ssaa.resolve(ResolveFilterEnum::BOX)), vec![(3, 4), (30, 20)]);
+}
+
+#[test]
+fn naive0_respects_its_clip() {
+    let clip = ClipRect::new(10, 10, 20, 20);
+    let mut plotted = Vec::new();
+    Naive0.draw(point(0, 0), point(39, 39), &clip, &mut |x, y, _| plotted.push((x, y)));
+    assert!(!plotted.is_empty());
+    assert!(plotted.iter().all(|&(x, y)| clip.contains(x, y)), "{:?}", plotted);
+}
+
+#[test]
+fn batched_lines_match_single_calls() {
+    let mut rng = Lcg::new(11);
+    let mut next = |bound: u32| rng.next(bound) - 5;
+    let segments: Vec<(Vec2i, Vec2i)> = (0..300).map(|_| (point(next(50), next(50)), point(next(50), next(50)))).collect();
+    let colors: Vec<[u8; 4]> = (0..300).map(|i| [i as u8, (i * 7) as u8, (i * 13) as u8, 255]).collect();
+
+    for &method in &[NAIVE1, NAIVE2, BRESENHAM, WU] {
+        let mut expected = canvas(40);
+        let mut actual   = canvas(40);
+        for (&(p0, p1), &color) in segments.iter().zip(&colors) {
+            line(p0, p1, color, &mut expected, method);
+        }
+        lines(&segments, &colors, &mut actual, method);
+        assert!(expected == actual, "{:?}", method);
+    }
+
+    // A single color is shared by every segment, custom methods work too
+    let mut expected = canvas(40);
+    let mut actual   = canvas(40);
+    for &(p0, p1) in &segments {
+        line(p0, p1, [255, 0, 0, 255], &mut expected, &Doubled);
+    }
+    lines(&segments, &[[255, 0, 0, 255]], &mut actual, &Doubled);
+    assert!(expected == actual);
+}
+
+// Run with `cargo test --release -- --ignored --nocapture` to compare
+// `lines` with one `line` call per segment
+#[test]
+#[ignore]
+fn batched_lines_throughput() {
+    use std::time::Instant;
+
+    let mut rng = Lcg::new(5);
+    let segments: Vec<(Vec2i, Vec2i)> = (0..200_000)
+        .map(|_| (point(rng.next(512), rng.next(512)), point(rng.next(512), rng.next(512))))
+        .map(|(p0, p1)| (p0, point(p0.x.value + (p1.x.value - p0.x.value) / 32, p0.y.value + (p1.y.value - p0.y.value) / 32)))
+        .collect();
+    for &method in &[BRESENHAM, WU] {
+        let mut single = RgbaImage::new(512, 512);
+        let start = Instant::now();
+        for &(p0, p1) in &segments {
+            line(p0, p1, [255, 255, 255, 255], &mut single, method);
+        }
+        let single_time = start.elapsed();
+
+        let mut batched = RgbaImage::new(512, 512);
+        let start = Instant::now();
+        lines(&segments, &[[255, 255, 255, 255]], &mut batched, method);
+        let batched_time = start.elapsed();
+
+        assert!(single == batched);
+        println!("{:?}: {} segments, line {:?}, lines {:?}", method, segments.len(), single_time, batched_time);
+    }
+}
+
+fn lit_pixels(p0: Vec2i, p1: Vec2i, method: render_with_rust::line::LineMethodEnum) -> Vec<(u32, u32)> {
+    let mut img = RgbaImage::new(64, 64);
+    line(p0, p1, [255, 255, 255, 255], &mut img, method);
+    img.enumerate_pixels().filter(|(_, _, p)| p[0] > 0).map(|(x, y, _)| (x, y)).collect()
+}
+
+// Every octant, both directions, exact-half slopes and degenerate segments
+fn assert_matches_bresenham(method: render_with_rust::line::LineMethodEnum) {
+    let mut segments = vec![(point(5, 5), point(5, 5)), (point(0, 0), point(63, 63)),
+                            (point(10, 3), point(12, 60)), (point(2, 20), point(60, 21)),
+                            (point(4, 30), point(60, 30)), (point(40, 2), point(40, 61))];
+    for &(x, y) in &[(31, 11), (-31, 11), (31, -11), (-31, -11), (11, 31), (-11, 31), (11, -31), (-11, -31),
+                     (30, 15), (15, 30), (-30, 15), (30, -3), (3, 30)] {
+        segments.push((point(32, 32), point(32 + x, 32 + y)));
+        segments.push((point(32 + x, 32 + y), point(32, 32)));
+    }
+    let mut rng = Lcg::new(5);
+    let mut next = || rng.next(64);
+    for _ in 0..200 {
+        segments.push((point(next(), next()), point(next(), next())));
+    }
+
+    for (p0, p1) in segments {
+        assert_eq!(lit_pixels(p0, p1, method), lit_pixels(p0, p1, BRESENHAM), "{:?} {:?} -> {:?}", method, p0, p1);
+    }
+}
+
+#[test]
+fn dda_matches_bresenham() {
+    assert_matches_bresenham(DDA);
+}
+
+#[test]
+fn efla_matches_bresenham() {
+    assert_matches_bresenham(EFLA);
+}
+
+#[test]
+fn double_step_matches_bresenham() {
+    assert_matches_bresenham(DOUBLESTEP);
+}
+
+#[test]
+fn run_slice_matches_bresenham() {
+    assert_matches_bresenham(RUNSLICE);
+}
+
+#[test]
+fn steep_lines_draw_in_every_direction() {
+    // Regression: steep segments were ordered by x instead of the major axis
+    for &method in &[NAIVE1, NAIVE2, BRESENHAM] {
+        assert_eq!(lit_pixels(point(10, 50), point(12, 10), method).len(), 40, "{:?}", method);
+    }
+}
+
+#[test]
+fn lines_in_3d_are_depth_tested() {
+    let camera = Camera::look_at(vec3(0.0, 0.0, 5.0), vec3(0.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0));
+    let face   = camera.project(vec3(0.0, 0.0, 0.0), 40, 40).unwrap().z.value;
+    let draw = |z: f32, bias: f32| {
+        // A face at z = 0 covers the whole view
+        let mut img   = canvas(40);
+        let mut depth = DepthBuffer::from_pixel(40, 40, image::Luma([face]));
+        line_3d(vec3(-1.0, 0.2, z), vec3(1.0, -0.3, z), [255, 255, 255, 255], &camera, &mut img, &mut depth, BRESENHAM, bias);
+        img.pixels().filter(|p| p[0] > 0).count()
+    };
+
+    let in_front = draw(1.0, 0.0);
+    assert!(in_front > 10);
+    assert_eq!(draw(-1.0, 0.0), 0);
+    // An edge lying on the face shows up completely once biased
+    let on_face = draw(0.0, 1e-4);
+    assert!(on_face > 10 && on_face == draw(0.0, 1e-2));
+}
+
+#[test]
+fn lines_in_3d_clip_at_the_near_plane() {
+    let camera = Camera::look_at(vec3(0.0, 0.0, 5.0), vec3(0.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0));
+    let mut img   = canvas(40);
+    let mut depth = DepthBuffer::from_pixel(40, 40, image::Luma([f32::INFINITY]));
+
+    // Runs from in front of the camera to behind it
+    line_3d(vec3(0.5, -0.5, 0.0), vec3(0.5, -0.5, 10.0), [255, 255, 255, 255], &camera, &mut img, &mut depth, BRESENHAM, 0.0);
+    assert!(img.pixels().any(|p| p[0] > 0));
+    // Entirely behind the camera
+    let mut behind = canvas(40);
+    line_3d(vec3(-1.0, 0.0, 6.0), vec3(1.0, 0.0, 7.0), [255, 255, 255, 255], &camera, &mut behind, &mut depth, BRESENHAM, 0.0);
+    assert!(behind.pixels().all(|p| p[0] == 0));
+    // Only the visible part wrote depth, all of it inside the view volume
+    let nearest = depth.pixels().map(|p| p[0]).fold(f32::INFINITY, f32::min);
+    assert!((0.0..1.0).contains(&nearest));
+}
+
+#[test]
+fn far_apart_endpoints_do_not_overflow() {
+    let (lo, hi) = (i32::MIN / 2, i32::MAX / 2);
+    for method in [NAIVE0, NAIVE1, NAIVE2, BRESENHAM, WU, DDA, EFLA, DOUBLESTEP, RUNSLICE] {
+        let mut img = canvas(40);
+        line(point(lo, lo), point(hi, hi), [255, 0, 0, 255], &mut img, method);
+        line(point(hi, 20), point(lo, 20), [255, 0, 0, 255], &mut img, method);
+        line(point(5, lo), point(5, hi), [255, 0, 0, 255], &mut img, method);
+        if method == NAIVE0 {continue;}
+        assert!((0..40).all(|x| img.get_pixel(x, 20)[0] > 0 && img.get_pixel(5, x)[0] > 0), "{:?}", method);
+        if method != NAIVE1 {
+            assert!((0..40).all(|x| img.get_pixel(x, x)[0] > 0), "{:?}", method);
+        }
+    }
+
+    // The entry points built on top of the methods, over the full i32 range
+    let (p0, p1) = (point(i32::MIN, 20), point(i32::MAX, 20));
+    let across = |img: &RgbaImage| (0..40).all(|x| img.get_pixel(x, 20)[0] > 0);
+    for method in [BRESENHAM, WU] {
+        let mut img = canvas(40);
+        line_patterned(p0, p1, [255, 0, 0, 255], &mut img, method, &LinePatternEnum::dash(&[3.0, 2.0], 0.0));
+        assert!((0..40).any(|x| img.get_pixel(x, 20)[0] > 0), "{:?}", method);
+        let mut img = canvas(40);
+        polyline(&[p0, p1, point(20, i32::MIN)], [255, 0, 0, 255], &mut img, method, &LinePatternEnum::SOLID);
+        assert!(across(&img), "{:?}", method);
+        let mut img = canvas(40);
+        line_gradient(LineVertex::new(p0, [255, 0, 0, 255]), LineVertex::new(p1, [255, 0, 255, 255]),
+                      &mut img, method, GradientModeEnum::LINEAR);
+        assert!(across(&img), "{:?}", method);
+
+        let mut img = canvas(40);
+        let mut tiles = TileRenderer::new(16);
+        tiles.line(p0, p1, [255, 0, 0, 255], method);
+        tiles.render(&mut img);
+        assert!(across(&img), "{:?}", method);
+
+        let mut ssaa = Supersampler::new(40, 40, 4);
+        ssaa.line(point(-1_000_000_000, 20), point(1_000_000_000, 20), [255, 0, 0, 255], method);
+        assert!(across(&ssaa.resolve(ResolveFilterEnum::BOX)), "{:?}", method);
+    }
+}
+
+#[test]
+fn wu_polyline_joints_merge_coverage() {
+    let white = [255, 255, 255, 255];
+    let solid = LinePatternEnum::SOLID;
+
+    // A straight run split in two keeps the joint as bright as its neighbours
+    let mut split = canvas(40);
+    let mut whole = canvas(40);
+    polyline(&[point(2, 10), point(20, 10), point(38, 10)], white, &mut split, WU, &solid);
+    line(point(2, 10), point(38, 10), white, &mut whole, WU);
+    assert_eq!(split.get_pixel(20, 10), whole.get_pixel(20, 10));
+    assert_eq!(split.get_pixel(20, 10)[0], 255);
+
+    // At a bend the joint pixel gets both segments' halves, blended once
+    let (a, b, c) = (point(3, 30), point(20, 12), point(36, 25));
+    let mut bent = canvas(40);
+    polyline(&[a, b, c], white, &mut bent, WU, &solid);
+    let (mut first, mut second) = (canvas(40), canvas(40));
+    line(a, b, white, &mut first, WU);
+    line(b, c, white, &mut second, WU);
+    let halves = first.get_pixel(20, 12)[0] as u32 + second.get_pixel(20, 12)[0] as u32;
+    assert!((bent.get_pixel(20, 12)[0] as u32).abs_diff(halves.min(255)) <= 1);
+    for (x, y, p) in bent.enumerate_pixels() {
+        if (x, y) != (20, 12) {
+            assert!(p[0] >= first.get_pixel(x, y)[0].max(second.get_pixel(x, y)[0]));
+        }
+    }
+}
@@ -0,0 +1,282 @@
+mod common;
+
+use std::collections::{HashMap, HashSet};
+use render_with_rust::{
+    model::{triangle, triangle_subpixel, read_obj, Mesh, ObjError, TriangleMethodEnum, TriangleMethodEnum::*},
+    backend::geometry::*
+};
+use common::{point, pt, canvas, lit, Lcg};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+#[test]
+fn triangle_methods_cover_the_same_pixels() {
+    let mut rng = Lcg::new(3);
+    let mut next = |bound: u32| rng.next(bound) - 8;
+    for _ in 0..300 {
+        // Some vertices fall off the canvas to exercise clipping
+        let (t0, t1, t2) = (point(next(80), next(80)), point(next(80), next(80)), point(next(80), next(80)));
+        let (mut scanline, mut edge) = (canvas(64), canvas(64));
+        triangle(t0, t1, t2, WHITE, &mut scanline, SCANLINE);
+        triangle(t0, t1, t2, WHITE, &mut edge, EDGEFUNCTION);
+        assert_eq!(lit(&scanline), lit(&edge), "triangle {:?} {:?} {:?}", t0, t1, t2);
+
+        // Quarter-pixel vertices put many centers exactly on edges
+        let mut quarter = || pt(next(320) as f32 * 0.25, next(320) as f32 * 0.25);
+        let (v0, v1, v2) = (quarter(), quarter(), quarter());
+        let (mut scanline, mut edge) = (canvas(64), canvas(64));
+        triangle_subpixel(v0, v1, v2, WHITE, &mut scanline, SCANLINE);
+        triangle_subpixel(v0, v1, v2, WHITE, &mut edge, EDGEFUNCTION);
+        assert_eq!(lit(&scanline), lit(&edge), "triangle {:?} {:?} {:?}", v0, v1, v2);
+    }
+}
+
+#[test]
+fn triangles_cover_pixel_centers() {
+    for method in [SCANLINE, EDGEFUNCTION] {
+        let mut img = canvas(64);
+        triangle(point(4, 4), point(20, 4), point(4, 20), WHITE, &mut img, method);
+
+        // Centers on the hypotenuse (x + y = 23) belong to the triangle right of it
+        let expected: HashSet<_> = (4..20).flat_map(|y| (4..20).map(move |x| (x, y)))
+                                          .filter(|&(x, y)| x + y < 23).collect();
+        assert_eq!(lit(&img), expected);
+
+        // Winding and degenerate triangles
+        let mut flipped = canvas(64);
+        triangle(point(4, 4), point(4, 20), point(20, 4), WHITE, &mut flipped, method);
+        assert_eq!(lit(&flipped), expected);
+        let mut flat = canvas(64);
+        triangle(point(4, 4), point(12, 12), point(20, 20), WHITE, &mut flat, method);
+        assert!(lit(&flat).is_empty());
+    }
+}
+
+// How many of `triangles` cover each pixel
+fn counts(triangles: &[[Vec2f; 3]], method: TriangleMethodEnum) -> HashMap<(i32, i32), u32> {
+    let mut counts = HashMap::new();
+    for t in triangles {
+        let mut img = canvas(64);
+        triangle_subpixel(t[0], t[1], t[2], WHITE, &mut img, method);
+        for p in lit(&img) {
+            *counts.entry(p).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+#[test]
+fn tessellated_quads_cover_each_pixel_once() {
+    // Interior vertices are jittered by quarter pixels, so many pixel centers
+    // land exactly on shared edges and vertices
+    let mut rng = Lcg::new(5);
+    let mut jitter = || rng.next(17) as f32 * 0.25 - 2.0;
+    let n = 6;
+    let mut grid = vec![vec![pt(0.0, 0.0); n + 1]; n + 1];
+    for (j, row) in grid.iter_mut().enumerate() {
+        for (i, v) in row.iter_mut().enumerate() {
+            let inner = i > 0 && j > 0 && i < n && j < n;
+            let (dx, dy) = if inner {(jitter(), jitter())} else {(0.0, 0.0)};
+            *v = pt(4.25 + i as f32 * 9.0 + dx, 4.25 + j as f32 * 9.0 + dy);
+        }
+    }
+    let mut triangles = Vec::new();
+    for j in 0..n {
+        for i in 0..n {
+            let (a, b, c, d) = (grid[j][i], grid[j][i + 1], grid[j + 1][i + 1], grid[j + 1][i]);
+            // Alternate the diagonal and the winding
+            if (i + j) % 2 == 0 {triangles.extend([[a, b, c], [a, d, c]]);}
+            else {triangles.extend([[a, b, d], [b, c, d]]);}
+        }
+    }
+
+    // The outer square runs from 4.25 to 58.25, so centers 4.5 ..= 57.5 are inside
+    let inside: HashSet<_> = (4..58).flat_map(|y| (4..58).map(move |x| (x, y))).collect();
+    for method in [SCANLINE, EDGEFUNCTION] {
+        let counts = counts(&triangles, method);
+        assert!(counts.values().all(|&c| c == 1), "{:?} drew a pixel twice", method);
+        assert_eq!(counts.keys().copied().collect::<HashSet<_>>(), inside, "{:?}", method);
+    }
+}
+
+#[test]
+fn fans_cover_each_pixel_once() {
+    // A convex polygon with vertices on pixel centers, split two different ways
+    let ring: Vec<Vec2f> = (0..13).map(|i| {
+        let angle = i as f32 / 13.0 * std::f32::consts::TAU;
+        pt((32.0 + 24.0 * angle.cos()).round() + 0.5, (32.0 + 24.0 * angle.sin()).round() + 0.5)
+    }).collect();
+    let center = pt(32.5, 32.5);
+    let fan: Vec<_> = (0..ring.len()).map(|i| [center, ring[i], ring[(i + 1) % ring.len()]]).collect();
+    let from_vertex: Vec<_> = (1..ring.len() - 1).map(|i| [ring[0], ring[i], ring[i + 1]]).collect();
+
+    for method in [SCANLINE, EDGEFUNCTION] {
+        let (a, b) = (counts(&fan, method), counts(&from_vertex, method));
+        assert!(a.values().chain(b.values()).all(|&c| c == 1), "{:?} drew a pixel twice", method);
+        // Both split the same polygon, so they cover the same pixels
+        assert_eq!(a.keys().collect::<HashSet<_>>(), b.keys().collect::<HashSet<_>>());
+        assert!(a.contains_key(&(32, 32)));
+    }
+}
+
+#[test]
+fn far_vertices_keep_their_edges() {
+    // Vertices far past the fixed-point range; the slanted edge a -> b still
+    // has to cross the canvas where it really does
+    let (a, b, c) = (pt(-1e8, 0.0), pt(3e7, 60.0), pt(-1e8, 1e8));
+    let below = |x: f64, y: f64| {
+        let (ax, ay, bx, by) = (-1e8, 0.0, 3e7, 60.0);
+        let side = ((bx - ax) * (y - ay) - (by - ay) * (x - ax)) / (bx - ax).hypot(by - ay);
+        if side.abs() < 0.01 {None} else {Some(side > 0.0)}
+    };
+    for method in [SCANLINE, EDGEFUNCTION] {
+        let mut img = canvas(64);
+        triangle_subpixel(a, b, c, WHITE, &mut img, method);
+        let lit = lit(&img);
+        for y in 0..64 {
+            for x in 0..64 {
+                if let Some(inside) = below(x as f64 + 0.5, y as f64 + 0.5) {
+                    assert_eq!(lit.contains(&(x, y)), inside, "{:?} at ({}, {})", method, x, y);
+                }
+            }
+        }
+        assert!(lit.contains(&(32, 63)) && !lit.contains(&(32, 30)));
+    }
+}
+
+#[test]
+fn vertex_normals_average_the_faces() {
+    let v = |x: f32, y: f32, z: f32| Vec3f::new(Scalar::new(x), Scalar::new(y), Scalar::new(z));
+    let mut tetrahedron = Mesh::new(vec![v(1.0, 1.0, 1.0), v(1.0, -1.0, -1.0), v(-1.0, 1.0, -1.0), v(-1.0, -1.0, 1.0)],
+                                    vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]]);
+    // Counter-clockwise faces point out of the solid
+    for (t, face) in tetrahedron.triangles.iter().enumerate() {
+        let outward = face.iter().fold(v(0.0, 0.0, 0.0), |sum, &i| sum + tetrahedron.positions[i]);
+        assert!(tetrahedron.face_normal(t).dot(&outward) > 0.0);
+    }
+
+    tetrahedron.compute_normals();
+    for (n, p) in tetrahedron.normals.iter().zip(&tetrahedron.positions) {
+        assert!((n.dot(&p.normalize()) - 1.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn shared_edges_are_listed_once() {
+    let v = |x: f32, y: f32, z: f32| Vec3f::new(Scalar::new(x), Scalar::new(y), Scalar::new(z));
+    let quad = Mesh::new(vec![v(0.0, 0.0, 0.0), v(1.0, 0.0, 0.0), v(1.0, 1.0, 0.0), v(0.0, 1.0, 0.0)],
+                         vec![[0, 1, 2], [0, 2, 3]]);
+    assert_eq!(quad.edges().len(), 5);
+
+    // A cube with separate vertices per face still has 12 sides and 6 diagonals
+    let mut positions = Vec::new();
+    let mut triangles = Vec::new();
+    for axis in 0..3 {
+        for side in [-1.0, 1.0] {
+            let base = positions.len();
+            for (a, b) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                let mut c = [0.0; 3];
+                c[axis] = side;
+                c[(axis + 1) % 3] = a;
+                c[(axis + 2) % 3] = b;
+                positions.push(v(c[0], c[1], c[2]));
+            }
+            triangles.extend([[base, base + 1, base + 2], [base, base + 2, base + 3]]);
+        }
+    }
+    assert_eq!(Mesh::new(positions, triangles).edges().len(), 18);
+}
+
+fn obj(text: &str) -> Result<Mesh, ObjError> {
+    read_obj(&mut text.as_bytes())
+}
+
+#[test]
+fn obj_faces_groups_and_negative_indices() {
+    let mesh = obj("# a quad and a triangle
+mtllib scene.mtl
+o board
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+g front face
+usemtl wood
+f 1/1/1 2/2/1 3/3/1 4/4/1   # triangulated as a fan
+o flag
+v 0 0 1
+f -2//-1 -3//1 -1//1
+").unwrap();
+
+    // The last face uses new position and normal pairs, so it gets new vertices
+    assert_eq!(mesh.triangles.len(), 3);
+    assert_eq!(mesh.positions.len(), 7);
+    assert_eq!(mesh.normals.len(), 7);
+    assert_eq!(mesh.texcoords.len(), 7);
+    // The fan shares its first corner, and relative indices count back from the end
+    assert_eq!(mesh.triangles[0][0], mesh.triangles[1][0]);
+    let last = mesh.triangles[2].map(|i| mesh.positions[i].z.value);
+    assert_eq!(last, [0.0, 0.0, 1.0]);
+    assert_eq!(mesh.positions[mesh.triangles[2][0]], mesh.positions[mesh.triangles[1][2]]);
+    assert_eq!(mesh.positions[mesh.triangles[2][1]], mesh.positions[mesh.triangles[0][2]]);
+    // Corners without texture coordinates get zeros
+    assert_eq!(mesh.texcoords[mesh.triangles[2][2]].x.value, 0.0);
+    assert_eq!(mesh.texcoords[mesh.triangles[0][2]].y.value, 1.0);
+
+    assert_eq!(mesh.groups.len(), 2);
+    assert_eq!((mesh.groups[0].object.as_str(), mesh.groups[0].name.as_str()), ("board", "front face"));
+    assert_eq!(mesh.groups[0].triangles, 0..2);
+    assert_eq!((mesh.groups[1].object.as_str(), mesh.groups[1].name.as_str()), ("flag", "front face"));
+    assert_eq!(mesh.groups[1].triangles, 2..3);
+}
+
+#[test]
+fn obj_smoothing_groups_decide_vertex_normals() {
+    // Two faces folded along the edge between vertices 2 and 3
+    let tent = |faces: &str| obj(&format!("v 0 0 0\nv 1 0 1\nv 1 1 1\nv 2 0 0\n{}", faces)).unwrap();
+
+    let smooth = tent("s 1\nf 1 2 3\nf 2 4 3\n");
+    assert_eq!(smooth.positions.len(), 4);
+    let ridge = smooth.normals[1];
+    assert!(ridge.x.value.abs() < 1e-6 && (ridge.z.value - 1.0).abs() < 1e-6);
+
+    // Separate groups, or smoothing turned off for a face, keep the crease
+    for faces in ["s 1\nf 1 2 3\ns 2\nf 2 4 3\n", "s 1\nf 1 2 3\ns off\nf 2 4 3\n"] {
+        let faceted = tent(faces);
+        assert_eq!(faceted.positions.len(), 6);
+        for t in 0..2 {
+            for &i in &faceted.triangles[t] {
+                assert!((faceted.normals[i].dot(&faceted.face_normal(t)) - 1.0).abs() < 1e-6);
+            }
+        }
+    }
+
+    // Without normals or smoothing the vertices stay shared and normals empty
+    for faces in ["f 1 2 3\nf 2 4 3\n", "s off\nf 1 2 3\nf 2 4 3\n"] {
+        let plain = tent(faces);
+        assert_eq!(plain.positions.len(), 4);
+        assert!(plain.normals.is_empty() && plain.texcoords.is_empty() && plain.groups.len() == 1);
+    }
+}
+
+#[test]
+fn obj_errors_report_line_numbers() {
+    let error = |text: &str| match obj(text) {
+        Err(ObjError::Parse{line, message}) => (line, message),
+        other => panic!("expected a parse error, got {:?}", other),
+    };
+    assert_eq!(error("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n").0, 4);
+    assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 -4\n").0, 4);
+    assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n").0, 4);
+    assert_eq!(error("v 0 0 0\nv 1 0 0\nf 1 2\n").0, 3);
+    assert_eq!(error("# comment\nv 0 zero 0\n").0, 2);
+    assert_eq!(error("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2 3\n").0, 4);
+
+    let message = obj("v 0 0 0\nf 1 1 7\n").unwrap_err().to_string();
+    assert_eq!(message, "malformed obj data at line 2: vertex index 7 out of range, 1 defined");
+}
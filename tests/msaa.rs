@@ -0,0 +1,42 @@
+use render_with_rust::{
+    backend::geometry::*,
+    msaa::{MsaaTarget, SampleCountEnum::*}
+};
+
+fn vertex(x: f32, y: f32, z: f32) -> Vec3f {
+    Vec3f::new(Scalar::new(x), Scalar::new(y), Scalar::new(z))
+}
+
+#[test]
+fn edges_get_fractional_coverage() {
+    for samples in [MSAA2, MSAA4, MSAA8] {
+        let mut target = MsaaTarget::new(8, 8, samples);
+        target.clear([0, 0, 0, 255], f32::INFINITY);
+        // Diagonal half of the image, edge running through pixel centers
+        target.triangle(vertex(0.0, 0.0, 0.5), vertex(8.0, 0.0, 0.5), vertex(0.0, 8.0, 0.5), [255, 255, 255, 255]);
+        let image = target.resolve();
+
+        assert_eq!(image.get_pixel(1, 1)[0], 255);
+        assert_eq!(image.get_pixel(7, 7)[0], 0);
+        let edge = image.get_pixel(3, 4)[0];
+        assert!(edge > 0 && edge < 255, "{:?} edge value {}", samples, edge);
+    }
+}
+
+#[test]
+fn per_sample_depth_test() {
+    let mut target = MsaaTarget::new(4, 4, MSAA4);
+    target.clear([0, 0, 0, 255], f32::INFINITY);
+    target.triangle(vertex(0.0, 0.0, 0.2), vertex(8.0, 0.0, 0.2), vertex(0.0, 8.0, 0.2), [255, 0, 0, 255]);
+    target.triangle(vertex(0.0, 0.0, 0.8), vertex(8.0, 0.0, 0.8), vertex(0.0, 8.0, 0.8), [0, 255, 0, 255]);
+    assert_eq!(target.resolve().get_pixel(1, 1).0, [255, 0, 0, 255]);
+
+    let mut shaded = 0;
+    target.triangle_shaded(vertex(-1.0, -1.0, 0.1), vertex(20.0, -1.0, 0.1), vertex(-1.0, 20.0, 0.1),
+                           &mut |_, _, _| {shaded += 1; [0, 0, 255, 255]});
+    assert_eq!(shaded, 16);    // once per pixel, not once per sample
+
+    let image = target.resolve();
+    assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+    assert!((target.resolve_depth().get_pixel(0, 0)[0] - 0.1).abs() < 1e-6);
+}
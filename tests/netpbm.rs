@@ -0,0 +1,80 @@
+use image::{RgbaImage, Rgba, Rgb, Luma};
+use render_with_rust::backend::{images::{HdrImage, DepthBuffer}, netpbm::*};
+
+fn gradient() -> RgbaImage {
+    RgbaImage::from_fn(13, 7, |x, y| Rgba([(x * 19) as u8, (y * 36) as u8, ((x + y) * 11) as u8, (x * y * 3) as u8]))
+}
+
+fn round_trip(image: &RgbaImage, format: NetpbmFormat, encoding: NetpbmEncoding) -> RgbaImage {
+    let mut bytes = Vec::new();
+    write_pnm(&mut bytes, image, format, encoding).unwrap();
+    read_pnm(&mut bytes.as_slice()).unwrap()
+}
+
+#[test]
+fn pnm_round_trips() {
+    let image = gradient();
+    for &encoding in &[NetpbmEncoding::ASCII, NetpbmEncoding::BINARY] {
+        let ppm = round_trip(&image, NetpbmFormat::PPM, encoding);
+        assert!(ppm.pixels().zip(image.pixels()).all(|(a, b)| a.0[..3] == b.0[..3] && a[3] == 255));
+
+        let pgm = round_trip(&image, NetpbmFormat::PGM, encoding);
+        assert!(pgm.pixels().all(|p| p[0] == p[1] && p[1] == p[2]));
+
+        let pbm = round_trip(&image, NetpbmFormat::PBM, encoding);
+        assert!(pbm.pixels().all(|p| p[0] == 0 || p[0] == 255));
+    }
+    assert!(round_trip(&image, NetpbmFormat::PAM, NetpbmEncoding::BINARY) == image);
+    assert!(write_pnm(&mut Vec::new(), &image, NetpbmFormat::PAM, NetpbmEncoding::ASCII).is_err());
+}
+
+#[test]
+fn reads_comments_and_wide_samples() {
+    let ascii = b"P2\n# comment\n2 1 # trailing\n65535\n0 65535\n";
+    let image = read_pnm(&mut &ascii[..]).unwrap();
+    assert_eq!(*image.get_pixel(1, 0), Rgba([255, 255, 255, 255]));
+
+    let pbm = b"P1 3 1 101";
+    let image = read_pnm(&mut &pbm[..]).unwrap();
+    assert_eq!(image.get_pixel(0, 0)[0], 0);
+    assert_eq!(image.get_pixel(1, 0)[0], 255);
+
+    assert!(read_pnm(&mut &b"P6\n4 4\n255\n\x00"[..]).is_err());
+}
+
+#[test]
+fn pfm_round_trips() {
+    let hdr = HdrImage::from_fn(5, 3, |x, y| Rgb([x as f32 * 1.5, -(y as f32), 1e6]));
+    let mut bytes = Vec::new();
+    write_pfm(&mut bytes, &hdr).unwrap();
+    assert!(read_pfm(&mut bytes.as_slice()).unwrap() == hdr);
+
+    let depth = DepthBuffer::from_fn(4, 6, |x, y| Luma([(x + 10 * y) as f32 * 0.25]));
+    let mut bytes = Vec::new();
+    write_pfm_depth(&mut bytes, &depth).unwrap();
+    assert!(read_pfm_depth(&mut bytes.as_slice()).unwrap() == depth);
+
+    let mut color = Vec::new();
+    write_pfm(&mut color, &hdr).unwrap();
+    assert!(read_pfm_depth(&mut color.as_slice()).is_err());
+}
+
+#[test]
+fn pfm_rejects_bad_dimensions() {
+    assert!(read_pfm(&mut &b"PF\n0 1\n-1.0\n"[..]).is_err());
+    assert!(read_pfm_depth(&mut &b"Pf\n3 0\n-1.0\n"[..]).is_err());
+    assert!(read_pfm(&mut &b"PF\n4294967295 4294967295\n-1.0\n\x00"[..]).is_err());
+}
+
+#[test]
+fn plain_lines_stay_within_70_characters() {
+    let wide = RgbaImage::from_fn(97, 3, |x, y| Rgba([(x * 37 + y) as u8, 200, (x * 3) as u8, 255]));
+    for &format in &[NetpbmFormat::PBM, NetpbmFormat::PGM, NetpbmFormat::PPM] {
+        let mut bytes = Vec::new();
+        write_pnm(&mut bytes, &wide, format, NetpbmEncoding::ASCII).unwrap();
+        let text = String::from_utf8(bytes.clone()).unwrap();
+        assert!(text.lines().all(|l| l.len() <= 70), "{:?}", format);
+        assert!(text.lines().count() > 6, "{:?}", format);
+        assert_eq!(read_pnm(&mut bytes.as_slice()).unwrap(), round_trip(&wide, format, NetpbmEncoding::BINARY));
+    }
+}
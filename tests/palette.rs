@@ -0,0 +1,49 @@
+use image::{RgbaImage, Rgba};
+use render_with_rust::backend::palette::{*, DitherMethodEnum::*};
+
+fn ramp() -> RgbaImage {
+    RgbaImage::from_fn(64, 16, |x, _| Rgba([(x * 4) as u8, (x * 4) as u8, (x * 4) as u8, 255]))
+}
+
+#[test]
+fn exact_palettes_round_trip() {
+    let colors = [[0, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 255], [255, 255, 255, 255]];
+    let image = RgbaImage::from_fn(8, 8, |x, y| Rgba(colors[((x + y) % 4) as usize]));
+
+    let generated = Palette::median_cut(&image, 16);
+    assert_eq!(generated.len(), 4);
+    for method in [NONE, BAYER4, BLUENOISE, FLOYDSTEINBERG, ATKINSON] {
+        assert!(quantize(&image, &generated, method).to_rgba() == image);
+    }
+}
+
+#[test]
+fn dithering_preserves_average_tone() {
+    let image = ramp();
+    let palette = Palette::new(vec![[0, 0, 0, 255], [255, 255, 255, 255]]);
+    let average = |img: &RgbaImage| img.pixels().map(|p| p[0] as f64).sum::<f64>() / (64.0 * 16.0);
+    let target = average(&image);
+
+    for method in [BAYER2, BAYER4, BAYER8, BLUENOISE, FLOYDSTEINBERG] {
+        let result = quantize(&image, &palette, method).to_rgba();
+        assert!((average(&result) - target).abs() < 8.0, "{:?} drifted", method);
+    }
+    // Atkinson drops a quarter of the error by design, so only check it stays two-tone
+    let atkinson = quantize(&image, &palette, ATKINSON).to_rgba();
+    assert!(atkinson.pixels().all(|p| p[0] == 0 || p[0] == 255));
+}
+
+#[test]
+fn kmeans_reduces_error() {
+    let image = RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 128, 255]));
+    let error = |palette: &Palette| {
+        let out = quantize(&image, palette, NONE).to_rgba();
+        out.pixels().zip(image.pixels())
+           .map(|(a, b)| (0..3).map(|c| (a[c] as f64 - b[c] as f64).powi(2)).sum::<f64>())
+           .sum::<f64>()
+    };
+    let median = Palette::median_cut(&image, 8);
+    let kmeans = Palette::kmeans(&image, 8, 10);
+    assert_eq!(kmeans.len(), 8);
+    assert!(error(&kmeans) <= error(&median));
+}
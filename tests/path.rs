@@ -0,0 +1,110 @@
+mod common;
+
+use std::collections::HashMap;
+use render_with_rust::{
+    path::*,
+    line::{ClipRect, LineMethodEnum::*},
+    stroke::{Stroke, LineCapEnum, LineJoinEnum},
+    backend::geometry::*
+};
+use common::{pt, canvas};
+
+fn rect(path: &mut Path, x0: f32, y0: f32, x1: f32, y1: f32) {
+    path.move_to(pt(x0, y0)).line_to(pt(x1, y0)).line_to(pt(x1, y1)).line_to(pt(x0, y1)).close();
+}
+
+#[test]
+fn curves_and_arcs_flatten_onto_the_curve() {
+    let mut path = Path::new();
+    path.move_to(pt(10.0, 30.0))
+        .quad_to(pt(30.0, 0.0), pt(50.0, 30.0))
+        .cubic_to(pt(50.0, 50.0), pt(10.0, 50.0), pt(10.0, 30.0))
+        .arc_to(pt(20.0, 20.0), 0.0, false, true, pt(50.0, 30.0));
+    let subs = path.flatten(0.1);
+    assert_eq!(subs.len(), 1);
+    let points = &subs[0].points;
+    assert!(points.len() > 10);
+    assert_eq!(*points.last().unwrap(), pt(50.0, 30.0));
+
+    // The arc is the upper half circle around (30, 30)
+    let arc: Vec<&Vec2f> = points.iter().rev().skip(1).take_while(|p| p.y.value < 30.0 - 1e-3).collect();
+    assert!(arc.len() > 5);
+    for p in arc {
+        let r = (p.x.value - 30.0).hypot(p.y.value - 30.0);
+        assert!((r - 20.0).abs() < 0.01, "{:?} at radius {}", p, r);
+    }
+}
+
+#[test]
+fn close_returns_to_the_subpath_start() {
+    let mut path = Path::new();
+    rect(&mut path, 10.0, 10.0, 20.0, 20.0);
+    path.line_to(pt(40.0, 40.0));
+    let subs = path.flatten(PATH_TOLERANCE);
+    assert_eq!(subs.len(), 2);
+    assert!(subs[0].closed && !subs[1].closed);
+    assert_eq!(subs[1].points, vec![pt(10.0, 10.0), pt(40.0, 40.0)]);
+}
+
+#[test]
+fn stroked_paths_plot_each_pixel_once() {
+    let mut path = Path::new();
+    rect(&mut path, 5.5, 5.5, 40.5, 30.5);
+    path.move_to(pt(10.0, 50.0)).cubic_to(pt(20.0, 20.0), pt(40.0, 80.0), pt(60.0, 40.0));
+    path.move_to(pt(50.0, 10.0)).arc_to(pt(6.0, 6.0), 0.0, true, false, pt(50.0, 11.0)).close();
+
+    for &method in &[NAIVE1, NAIVE2, BRESENHAM, WU] {
+        let mut hits: HashMap<(i32, i32), u32> = HashMap::new();
+        path.rasterize(method, &ClipRect::new(0, 0, 64, 64), &mut |x, y, _| {
+            *hits.entry((x, y)).or_insert(0) += 1;
+        });
+        assert!(hits.len() > 100);
+        assert!(hits.values().all(|&n| n == 1), "{:?} draws a pixel twice", method);
+    }
+}
+
+#[test]
+fn fill_rules() {
+    let mut path = Path::new();
+    rect(&mut path, 10.0, 10.0, 50.0, 50.0);
+    rect(&mut path, 20.0, 20.0, 40.0, 40.0);
+
+    let mut nonzero = canvas(64);
+    let mut evenodd = canvas(64);
+    path.fill([255, 255, 255, 255], FillRuleEnum::NONZERO, &mut nonzero);
+    path.fill([255, 255, 255, 255], FillRuleEnum::EVENODD, &mut evenodd);
+
+    for (x, y, p) in nonzero.enumerate_pixels() {
+        let outer = (10..50).contains(&x) && (10..50).contains(&y);
+        let inner = (20..40).contains(&x) && (20..40).contains(&y);
+        assert_eq!(p[0], if outer {255} else {0}, "nonzero ({}, {})", x, y);
+        assert_eq!(evenodd.get_pixel(x, y)[0], if outer && !inner {255} else {0}, "evenodd ({}, {})", x, y);
+    }
+}
+
+#[test]
+fn fill_edges_are_anti_aliased() {
+    let mut path = Path::new();
+    path.move_to(pt(10.0, 10.0)).line_to(pt(50.0, 10.0)).line_to(pt(10.0, 50.0));
+    let mut img = canvas(64);
+    path.fill([255, 255, 255, 255], FillRuleEnum::NONZERO, &mut img);
+
+    assert_eq!(img.get_pixel(15, 15)[0], 255);
+    let edge = img.get_pixel(29, 30)[0];
+    assert!(edge > 0 && edge < 255, "{}", edge);
+    assert_eq!(img.get_pixel(40, 40)[0], 0);
+}
+
+#[test]
+fn wide_stroke_blends_once_across_subpaths() {
+    let mut path = Path::new();
+    rect(&mut path, 10.0, 10.0, 40.0, 40.0);
+    path.move_to(pt(5.0, 25.0)).line_to(pt(55.0, 25.0));
+    let mut img = canvas(64);
+    path.stroke_wide([255, 255, 255, 128], &Stroke::new(4.0, LineCapEnum::ROUND, LineJoinEnum::ROUND), &mut img);
+
+    assert_eq!(img.get_pixel(25, 10)[0], 128);
+    // Where the crossing line overlaps the square's sides
+    assert_eq!(img.get_pixel(10, 25)[0], 128);
+    assert!(img.pixels().all(|p| p[0] <= 128));
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use image::RgbaImage;
+use render_with_rust::{
+    camera::Camera,
+    line::{ClipRect, DrawBehavior, LineMethodEnum::*},
+    model::{read_obj, Mesh, TriangleMethodEnum::*},
+    render::*,
+    backend::{images::DepthBuffer, geometry::Vec2i}
+};
+use common::{vec3, canvas};
+
+// Black canvas with a cleared depth buffer
+fn target() -> (RgbaImage, DepthBuffer) {
+    (canvas(64), DepthBuffer::from_pixel(64, 64, image::Luma([f32::INFINITY])))
+}
+
+fn camera() -> Camera {
+    Camera::look_at(vec3(0.0, 0.0, 5.0), vec3(0.0, 0.0, 0.0), vec3(0.0, 1.0, 0.0))
+}
+
+// Square facing +z at height `z`, with vertex normals along +z
+fn quad(size: f32, z: f32) -> Mesh {
+    let mut mesh = Mesh::new(vec![vec3(-size, -size, z), vec3(size, -size, z), vec3(size, size, z), vec3(-size, size, z)],
+                             vec![[0, 1, 2], [0, 2, 3]]);
+    mesh.compute_normals();
+    mesh
+}
+
+fn lit_pixels(img: &RgbaImage) -> Vec<[u8; 4]> {
+    img.pixels().filter(|p| p[0] > 0 || p[1] > 0 || p[2] > 0).map(|p| p.0).collect()
+}
+
+#[test]
+fn flat_shading_follows_lambert() {
+    // Light 60 degrees off the normal gives half the light, 188 once in sRGB
+    let angle = 60f32.to_radians();
+    let material = Material{ambient: 0.0, specular: 0.0, ..Material::default()};
+    let shading = Shading::new(ShadingModelEnum::FLAT, material)
+        .with_light(LightEnum::DIRECTIONAL{direction: vec3(angle.sin(), 0.0, -angle.cos()), color: [1.0; 3]});
+    let (mut img, mut depth) = target();
+    render_mesh(&quad(1.0, 0.0), &camera(), &shading, &mut img, &mut depth, EDGEFUNCTION);
+
+    let pixels = lit_pixels(&img);
+    assert!(pixels.len() > 300);
+    assert!(pixels.iter().all(|&p| p == [188, 188, 188, 255]));
+
+    // Light from behind leaves only the ambient term
+    let dark = Shading::new(ShadingModelEnum::FLAT, Material{ambient: 0.2, ..material})
+        .with_light(LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, 1.0), color: [1.0; 3]});
+    let (mut img, mut depth) = target();
+    render_mesh(&quad(1.0, 0.0), &camera(), &dark, &mut img, &mut depth, EDGEFUNCTION);
+    assert_eq!(img.get_pixel(32, 32).0, [124, 124, 124, 255]);
+}
+
+#[test]
+fn phong_catches_highlights_gouraud_misses() {
+    // A highlight in the middle of a large face falls between the vertices
+    let material = Material{color: [0, 0, 0, 255], ambient: 0.0, specular: 1.0, shininess: 64.0};
+    let light = LightEnum::POINT{position: vec3(0.0, 0.0, 5.0), color: [1.0; 3]};
+    let render = |model| {
+        let (mut img, mut depth) = target();
+        render_mesh(&quad(1.5, 0.0), &camera(), &Shading::new(model, material).with_light(light), &mut img, &mut depth, SCANLINE);
+        img
+    };
+    let gouraud = render(ShadingModelEnum::GOURAUD);
+    let phong   = render(ShadingModelEnum::PHONG);
+
+    assert!(phong.get_pixel(32, 32)[0] >= 250);
+    assert!(gouraud.get_pixel(32, 32)[0] < 100);
+    // Phong falls off away from the highlight, while the equally lit corners
+    // leave Gouraud the same everywhere
+    assert!(phong.get_pixel(40, 32)[0] < phong.get_pixel(32, 32)[0]);
+    assert_eq!(gouraud.get_pixel(40, 32), gouraud.get_pixel(32, 32));
+}
+
+#[test]
+fn lights_add_up() {
+    let material = Material{ambient: 0.0, specular: 0.0, ..Material::default()};
+    let red  = LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, -1.0), color: [1.0, 0.0, 0.0]};
+    let blue = LightEnum::POINT{position: vec3(0.0, 0.0, 10.0), color: [0.0, 0.0, 1.0]};
+    let (mut img, mut depth) = target();
+    let shading = Shading::new(ShadingModelEnum::PHONG, material).with_light(red).with_light(blue);
+    render_mesh(&quad(1.0, 0.0), &camera(), &shading, &mut img, &mut depth, EDGEFUNCTION);
+
+    let center = img.get_pixel(32, 32).0;
+    assert_eq!(center[0], 255);
+    assert_eq!(center[1], 0);
+    assert!(center[2] > 250);
+}
+
+#[test]
+fn nearer_faces_win_the_depth_test() {
+    let material = |color| Material{color, specular: 0.0, ..Material::default()};
+    let light = LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, -1.0), color: [1.0; 3]};
+    let near = Shading::new(ShadingModelEnum::FLAT, material([255, 0, 0, 255])).with_light(light);
+    let far  = Shading::new(ShadingModelEnum::FLAT, material([0, 255, 0, 255])).with_light(light);
+
+    for near_first in [true, false] {
+        let (mut img, mut depth) = target();
+        let draws = [(&quad(0.5, 1.0), &near), (&quad(1.0, 0.0), &far)];
+        let order: Vec<_> = if near_first {draws.to_vec()} else {draws.iter().rev().cloned().collect()};
+        for (mesh, shading) in order {
+            render_mesh(mesh, &camera(), shading, &mut img, &mut depth, EDGEFUNCTION);
+        }
+        assert_eq!(img.get_pixel(32, 32)[0], 255);
+        assert_eq!(img.get_pixel(32, 32)[1], 0);
+        // Outside the small quad the far one shows
+        assert_eq!(img.get_pixel(32, 42)[1], 255);
+    }
+}
+
+#[test]
+fn faces_crossing_the_near_plane_are_clipped() {
+    // A floor running from behind the eye into the distance, which the
+    // wireframe draws clipped to the near plane
+    let mut floor = Mesh::new(vec![vec3(-2.0, -1.0, 10.0), vec3(2.0, -1.0, 10.0), vec3(2.0, -1.0, -10.0), vec3(-2.0, -1.0, -10.0)],
+                              vec![[0, 1, 2], [0, 2, 3]]);
+    floor.compute_normals();
+    let shading = Shading::new(ShadingModelEnum::FLAT, Material{ambient: 1.0, ..Material::default()});
+    let (mut img, mut depth) = target();
+    render_mesh(&floor, &camera(), &shading, &mut img, &mut depth, SCANLINE);
+    let (mut edges, _) = target();
+    render_wireframe(&floor, &camera(), &Wireframe::new(BLUE, BRESENHAM), &mut edges, &mut depth);
+
+    // The bottom rows show the floor, the top half stays empty
+    assert_eq!(img.get_pixel(32, 63).0, [255, 255, 255, 255]);
+    assert_eq!(img.get_pixel(32, 20).0, [0, 0, 0, 255]);
+    // Every edge pixel, the diagonal through the near plane included, touches the surface
+    let near = |x: u32, y: u32| (x.saturating_sub(1)..=(x + 1).min(63))
+        .any(|i| (y.saturating_sub(1)..=(y + 1).min(63)).any(|j| img.get_pixel(i, j)[0] > 0));
+    let drawn: Vec<_> = edges.enumerate_pixels().filter(|(_, _, p)| p.0 == BLUE).map(|(x, y, _)| (x, y)).collect();
+    assert!(drawn.iter().any(|&(_, y)| y == 63));
+    assert!(drawn.iter().all(|&(x, y)| near(x, y)));
+}
+
+#[test]
+fn zero_normals_fall_back_to_the_face() {
+    let material = Material{ambient: 0.0, specular: 0.0, ..Material::default()};
+    let light = LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, -1.0), color: [1.0; 3]};
+    let mut mesh = quad(1.0, 0.0);
+    let zero = vec3(0.0, 0.0, 0.0);
+    mesh.normals = vec![zero; 4];
+    // A triangle without area has no face normal either
+    mesh.triangles.push([0, 0, 2]);
+    for model in [ShadingModelEnum::FLAT, ShadingModelEnum::GOURAUD, ShadingModelEnum::PHONG] {
+        let (mut img, mut depth) = target();
+        render_mesh(&mesh, &camera(), &Shading::new(model, material).with_light(light), &mut img, &mut depth, EDGEFUNCTION);
+        assert_eq!(img.get_pixel(32, 32).0, [255, 255, 255, 255], "{:?}", model);
+    }
+}
+
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+fn count_blue(img: &RgbaImage, x: std::ops::Range<u32>, y: std::ops::Range<u32>) -> usize {
+    img.enumerate_pixels().filter(|&(px, py, p)| x.contains(&px) && y.contains(&py) && p.0 == BLUE).count()
+}
+
+#[test]
+fn wireframe_draws_every_edge() {
+    let (mut img, mut depth) = target();
+    render_wireframe(&quad(1.0, 0.0), &camera(), &Wireframe::new(BLUE, BRESENHAM), &mut img, &mut depth);
+
+    // The square spans pixels 20..=43 on screen, plus its diagonal
+    assert_eq!(img.get_pixel(20, 32).0, BLUE);
+    assert_eq!(img.get_pixel(32, 20).0, BLUE);
+    assert_eq!(img.get_pixel(31, 32).0, BLUE);
+    assert_eq!(img.get_pixel(28, 32).0, [0, 0, 0, 255]);
+    // Without culling the depth buffer is left alone
+    assert!(depth.pixels().all(|d| d.0[0] == f32::INFINITY));
+}
+
+#[test]
+fn depth_buffers_of_another_size_are_respected() {
+    let shading = Shading::new(ShadingModelEnum::FLAT, Material::default())
+        .with_light(LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, -1.0), color: [1.0; 3]});
+    let buffer = |size: u32| DepthBuffer::from_pixel(size, size, image::Luma([f32::INFINITY]));
+    let inside = |img: &RgbaImage| RgbaImage::from_fn(32, 32, |x, y| *img.get_pixel(x, y));
+    let (mut full, mut depth) = target();
+    render_mesh(&quad(1.0, 0.0), &camera(), &shading, &mut full, &mut depth, SCANLINE);
+
+    // Only the pixels the smaller buffer reaches are drawn
+    let (mut img, mut small) = (canvas(64), buffer(32));
+    render_mesh(&quad(1.0, 0.0), &camera(), &shading, &mut img, &mut small, SCANLINE);
+    assert!(inside(&img) == inside(&full));
+    assert!(img.enumerate_pixels().all(|(x, y, p)| x < 32 && y < 32 || p.0 == [0, 0, 0, 255]));
+
+    // Hidden edges are culled against faces projected like the edges
+    let edges = |depth: &mut DepthBuffer| {
+        let mut img = canvas(64);
+        render_wireframe(&quad(1.0, 0.0), &camera(), &Wireframe::new(BLUE, BRESENHAM).culled(), &mut img, depth);
+        img
+    };
+    let reference = edges(&mut buffer(64));
+    assert!(count_blue(&reference, 0..64, 0..64) > 0);
+    assert!(edges(&mut buffer(128)) == reference);
+    assert!(inside(&edges(&mut buffer(32))) == inside(&reference));
+}
+
+// Plots only the two endpoints of every edge
+struct Corners;
+
+impl DrawBehavior for Corners {
+    fn draw(&self, p0: Vec2i, p1: Vec2i, _clip: &ClipRect, plot: &mut dyn FnMut(i32, i32, f32)) {
+        plot(p0.x.value, p0.y.value, 1.0);
+        plot(p1.x.value, p1.y.value, 1.0);
+    }
+}
+
+#[test]
+fn wireframe_takes_custom_methods() {
+    let (mut img, mut depth) = target();
+    render_wireframe(&quad(1.0, 0.0), &camera(), &Wireframe::new(BLUE, &Corners), &mut img, &mut depth);
+    assert_eq!(count_blue(&img, 0..64, 0..64), 4);
+}
+
+// A quad half as large `gap` in front of `quad(size, z)`, with its diagonal
+// the other way
+fn stacked(size: f32, z: f32, gap: f32) -> Mesh {
+    let mut scene = quad(size, z);
+    let (h, v) = (size * 0.5, |x, y| vec3(x, y, z + gap));
+    scene.positions.extend([v(-h, -h), v(h, -h), v(h, h), v(-h, h)]);
+    scene.triangles.extend([[4, 5, 7], [5, 6, 7]]);
+    scene.compute_normals();
+    scene
+}
+
+#[test]
+fn culled_wireframe_hides_edges_behind_the_surface() {
+    let scene = stacked(1.0, 0.0, 1.0);
+    let shading = Shading::new(ShadingModelEnum::FLAT, Material::default())
+        .with_light(LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, -1.0), color: [1.0; 3]});
+
+    let draw = |wireframe: Wireframe, shaded: bool| {
+        let (mut img, mut depth) = target();
+        if shaded {render_mesh(&scene, &camera(), &shading, &mut img, &mut depth, SCANLINE);}
+        render_wireframe(&scene, &camera(), &wireframe, &mut img, &mut depth);
+        img
+    };
+    let all     = draw(Wireframe::new(BLUE, BRESENHAM), false);
+    let culled  = draw(Wireframe::new(BLUE, BRESENHAM).culled(), false);
+    let overlay = draw(Wireframe::new(BLUE, BRESENHAM).culled(), true);
+
+    // Inside the near quad both diagonals show unless the far one is culled
+    let inside = (28..37, 28..37);
+    let (seen, visible) = (count_blue(&all, inside.0.clone(), inside.1.clone()),
+                           count_blue(&culled, inside.0.clone(), inside.1.clone()));
+    assert!(visible > 0 && visible < seen, "{} of {}", visible, seen);
+    assert_eq!(count_blue(&overlay, inside.0.clone(), inside.1.clone()), visible);
+    // The far quad's outline stays visible around the near one
+    assert_eq!(count_blue(&culled, 0..64, 0..24), count_blue(&all, 0..64, 0..24));
+    assert!(count_blue(&culled, 0..64, 0..24) > 0);
+    // The overlay keeps the shaded faces between the edges
+    assert_eq!(overlay.get_pixel(30, 35).0, [255, 255, 255, 255]);
+}
+
+#[test]
+fn culled_wireframe_bias_holds_up_far_away() {
+    // The quads are 5 units apart at a distance of 85, where that is a far
+    // smaller step in screen depth than right in front of the camera
+    let scene = stacked(40.0, -85.0, 5.0);
+    let near = Mesh::new(scene.positions[4..].to_vec(), vec![[0, 1, 3], [1, 2, 3]]);
+    let draw = |mesh: &Mesh, wireframe: Wireframe| {
+        let (mut img, mut depth) = target();
+        render_wireframe(mesh, &camera(), &wireframe, &mut img, &mut depth);
+        img
+    };
+    let culled = draw(&scene, Wireframe::new(BLUE, BRESENHAM).culled());
+    let front  = draw(&near, Wireframe::new(BLUE, BRESENHAM));
+
+    // The near quad spans pixels 19..=45; inside it only its own diagonal shows
+    let inside = (22..43, 22..43);
+    let visible = count_blue(&culled, inside.0.clone(), inside.1.clone());
+    assert!(visible > 0);
+    assert_eq!(visible, count_blue(&front, inside.0, inside.1));
+}
+
+#[test]
+fn loaded_obj_renders_shaded_with_a_wireframe() {
+    let octahedron = "o diamond\ns 1
+v 1 0 0\nv -1 0 0\nv 0 1 0\nv 0 -1 0\nv 0 0 1\nv 0 0 -1
+f 1 3 5\nf 3 2 5\nf 2 4 5\nf 4 1 5\nf 3 1 6\nf 2 3 6\nf 4 2 6\nf 1 4 6
+";
+    let mesh = read_obj(&mut octahedron.as_bytes()).unwrap();
+    let shading = Shading::new(ShadingModelEnum::PHONG, Material{color: [255, 0, 0, 255], ..Material::default()})
+        .with_light(LightEnum::DIRECTIONAL{direction: vec3(0.0, 0.0, -1.0), color: [1.0; 3]});
+    let (mut img, mut depth) = target();
+    render_mesh(&mesh, &camera(), &shading, &mut img, &mut depth, EDGEFUNCTION);
+    render_wireframe(&mesh, &camera(), &Wireframe::new(BLUE, WU).culled(), &mut img, &mut depth);
+
+    // Smoothed normals light the tip facing the camera fully
+    assert_eq!(img.get_pixel(30, 28)[0], 255);
+    assert!(count_blue(&img, 0..64, 0..64) > 40);
+    assert_eq!(img.get_pixel(2, 2).0, [0, 0, 0, 255]);
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use std::collections::HashSet;
+use render_with_rust::{
+    shapes::*
+};
+use common::{point, pt, canvas, lit};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+#[test]
+fn midpoint_circle_is_symmetric_and_round() {
+    let mut img = canvas(64);
+    circle(point(32, 32), 20, WHITE, &mut img);
+    let pixels = lit(&img);
+
+    for &(x, y) in &pixels {
+        let (dx, dy) = (x - 32, y - 32);
+        let r = ((dx * dx + dy * dy) as f32).sqrt();
+        assert!((r - 20.0).abs() <= 0.5, "({}, {}) at radius {}", x, y, r);
+        for &(mx, my) in &[(-dx, dy), (dx, -dy), (dy, dx), (-dy, -dx)] {
+            assert!(pixels.contains(&(32 + mx, 32 + my)));
+        }
+    }
+    assert!(pixels.contains(&(52, 32)) && pixels.contains(&(32, 12)));
+}
+
+#[test]
+fn filled_shapes_contain_their_outlines() {
+    let (mut outline, mut filled) = (canvas(64), canvas(64));
+    circle(point(30, 30), 15, WHITE, &mut outline);
+    circle_filled(point(30, 30), 15, WHITE, &mut filled);
+    let (o, f) = (lit(&outline), lit(&filled));
+    assert!(o.is_subset(&f));
+    let area = std::f32::consts::PI * 15.0 * 15.0;
+    assert!((f.len() as f32 - area).abs() / area < 0.1, "{} pixels", f.len());
+
+    let (mut outline, mut filled) = (canvas(64), canvas(64));
+    ellipse(point(32, 30), 25, 10, WHITE, &mut outline);
+    ellipse_filled(point(32, 30), 25, 10, WHITE, &mut filled);
+    let (o, f) = (lit(&outline), lit(&filled));
+    assert!(o.is_subset(&f));
+    for &p in &[(7, 30), (57, 30), (32, 20), (32, 40)] {
+        assert!(o.contains(&p), "{:?}", p);
+    }
+    assert!(!f.contains(&(6, 30)) && !f.contains(&(32, 41)));
+}
+
+#[test]
+fn shapes_off_the_image_are_clipped() {
+    let mut img = canvas(64);
+    circle_filled(point(0, 0), 30, WHITE, &mut img);
+    ellipse(point(60, 60), 40, 10, WHITE, &mut img);
+    assert!(img.get_pixel(0, 0)[0] == 255 && img.get_pixel(63, 63)[0] == 0);
+}
+
+#[test]
+fn huge_shapes_only_visit_the_image() {
+    // Only the top of a 30000 pixel disc reaches into the image
+    let mut img = canvas(40);
+    circle_filled(point(20, 30020), 30000, WHITE, &mut img);
+    let expected: HashSet<(i32, i32)> = (0..40).flat_map(|x| (20..40).map(move |y| (x, y))).collect();
+    assert_eq!(lit(&img), expected);
+
+    // Radii and centers at the ends of the i32 range
+    let mut img = canvas(40);
+    circle(point(i32::MAX, 20), i32::MAX, WHITE, &mut img);
+    let column: HashSet<(i32, i32)> = (0..40).map(|y| (0, y)).collect();
+    assert_eq!(lit(&img), column);
+    let mut img = canvas(40);
+    circle_filled(point(i32::MAX, 20), i32::MAX, WHITE, &mut img);
+    assert_eq!(lit(&img).len(), 40 * 40);
+
+    let mut img = canvas(40);
+    ellipse(point(i32::MAX, 20), i32::MAX, i32::MAX, WHITE, &mut img);
+    assert!(lit(&img).contains(&(0, 20)) && !lit(&img).contains(&(5, 20)));
+    let mut img = canvas(40);
+    ellipse_filled(point(20, i32::MIN + 40), i32::MAX, i32::MAX, WHITE, &mut img);
+    assert_eq!(lit(&img).len(), 40 * 40);
+}
+
+#[test]
+fn huge_sectors_only_visit_the_image() {
+    let (center, half_pi) = (point(20, 30020), std::f32::consts::FRAC_PI_2);
+    let mut disc = canvas(40);
+    circle_filled(center, 30000, WHITE, &mut disc);
+
+    // A thin wedge pointing up still covers the whole visible cap
+    let mut img = canvas(40);
+    pie(center, 30000, -half_pi - 0.1, -half_pi + 0.1, WHITE, &mut img);
+    assert_eq!(lit(&img), lit(&disc));
+    let mut img = canvas(40);
+    pie(center, 30000, 0.0, 1.0, WHITE, &mut img);
+    assert!(lit(&img).is_empty());
+
+    // The ring is the disc minus the one inside the inner radius
+    let (mut img, mut hole) = (canvas(40), canvas(40));
+    annular_sector(center, 29990, 30000, 0.0, 7.0, WHITE, &mut img);
+    circle_filled(center, 29989, WHITE, &mut hole);
+    let ring: HashSet<_> = lit(&disc).difference(&lit(&hole)).copied().collect();
+    assert!(!ring.is_empty() && !lit(&hole).is_empty());
+    assert_eq!(lit(&img), ring);
+}
+
+#[test]
+fn rotating_a_quarter_turn_swaps_the_radii() {
+    let (mut turned, mut swapped) = (canvas(64), canvas(64));
+    ellipse_rotated_filled(&Ellipse::new(pt(32.5, 32.5), pt(20.3, 8.7), std::f32::consts::FRAC_PI_2), WHITE, &mut turned);
+    ellipse_rotated_filled(&Ellipse::new(pt(32.5, 32.5), pt(8.7, 20.3), 0.0), WHITE, &mut swapped);
+    assert_eq!(lit(&turned), lit(&swapped));
+
+    // The outline is the filled set's boundary
+    let (mut outline, mut filled) = (canvas(64), canvas(64));
+    let tilted = Ellipse::new(pt(32.0, 32.0), pt(25.0, 9.0), 0.6);
+    ellipse_rotated(&tilted, WHITE, &mut outline);
+    ellipse_rotated_filled(&tilted, WHITE, &mut filled);
+    let (o, f) = (lit(&outline), lit(&filled));
+    assert!(o.is_subset(&f) && o.len() < f.len() / 2);
+    assert!(f.iter().filter(|p| !o.contains(p))
+             .all(|&(x, y)| [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)].iter().all(|q| f.contains(q))));
+}
+
+#[test]
+fn anti_aliased_ellipses_cover_their_area() {
+    let mut img = canvas(64);
+    let shape = Ellipse::new(pt(32.0, 32.0), pt(24.0, 12.0), 0.4);
+    ellipse_aa_filled(&shape, WHITE, &mut img);
+    let total: f32 = img.pixels().map(|p| p[0] as f32 / 255.0).sum();
+    let area = std::f32::consts::PI * 24.0 * 12.0;
+    assert!((total - area).abs() / area < 0.01, "{} vs {}", total, area);
+    assert_eq!(img.get_pixel(32, 32)[0], 255);
+    assert!(img.pixels().any(|p| p[0] > 0 && p[0] < 255));
+
+    // A one pixel ring carries about one pixel of coverage per unit length
+    let mut ring = canvas(64);
+    circle_aa(pt(32.0, 32.0), 20.0, 1.0, WHITE, &mut ring);
+    let total: f32 = ring.pixels().map(|p| p[0] as f32 / 255.0).sum();
+    let length = 2.0 * std::f32::consts::PI * 20.0;
+    assert!((total - length).abs() / length < 0.02, "{} vs {}", total, length);
+    assert_eq!(ring.get_pixel(32, 32)[0], 0);
+}
+
+#[test]
+fn adjacent_sectors_partition_the_disc() {
+    use std::f32::consts::PI;
+    let center = point(32, 32);
+    let mut whole = canvas(64);
+    circle_filled(center, 20, WHITE, &mut whole);
+    let mut ring = canvas(64);
+    circle(center, 20, WHITE, &mut ring);
+
+    // Pie-chart style cuts, with the last slice wrapping past a full turn
+    let cuts = [0.3, 1.9, 1.9 + PI / 4.0, 4.0, 0.3 + 2.0 * PI];
+    let mut covered = HashSet::new();
+    let mut outlined = HashSet::new();
+    for w in cuts.windows(2) {
+        let (mut slice, mut arc_img) = (canvas(64), canvas(64));
+        pie(center, 20, w[0], w[1], WHITE, &mut slice);
+        arc(center, 20, w[0], w[1], WHITE, &mut arc_img);
+        for p in lit(&slice) {
+            assert!(covered.insert(p), "pixel {:?} in two slices", p);
+        }
+        for p in lit(&arc_img) {
+            assert!(outlined.insert(p), "pixel {:?} in two arcs", p);
+        }
+    }
+    assert_eq!(covered, lit(&whole));
+    assert_eq!(outlined, lit(&ring));
+}
+
+#[test]
+fn sector_boundaries_are_exact() {
+    use std::f32::consts::PI;
+    let mut img = canvas(64);
+    pie(point(32, 32), 12, 0.0, PI / 4.0, WHITE, &mut img);
+
+    // The start ray is included, the end ray (the y-down diagonal) is not
+    assert_eq!(img.get_pixel(40, 32)[0], 255);
+    assert_eq!(img.get_pixel(40, 39)[0], 255);
+    assert_eq!(img.get_pixel(38, 38)[0], 0);
+    assert_eq!(img.get_pixel(40, 31)[0], 0);
+    // The apex belongs to the slice containing angle 0
+    assert_eq!(img.get_pixel(32, 32)[0], 255);
+
+    // A reflex sector keeps everything but the gap
+    let mut reflex = canvas(64);
+    pie(point(32, 32), 12, 0.0, 1.5 * PI, WHITE, &mut reflex);
+    assert_eq!(reflex.get_pixel(24, 28)[0], 255);
+    assert_eq!(reflex.get_pixel(28, 24)[0], 255);
+    assert_eq!(reflex.get_pixel(32, 24)[0], 0);
+    assert_eq!(reflex.get_pixel(36, 26)[0], 0);
+}
+
+#[test]
+fn annular_sectors_stay_between_the_radii() {
+    use std::f32::consts::PI;
+    let center = point(32, 32);
+    let mut img = canvas(64);
+    annular_sector(center, 8, 16, -PI, PI, WHITE, &mut img);
+
+    // A full turn is the filled outer circle minus the inside of the inner one
+    let mut outer = canvas(64);
+    circle_filled(center, 16, WHITE, &mut outer);
+    let mut hole = canvas(64);
+    circle_filled(center, 7, WHITE, &mut hole);
+    let expected: HashSet<_> = lit(&outer).difference(&lit(&hole)).copied().collect();
+    assert_eq!(lit(&img), expected);
+
+    // Both outlines are part of the ring
+    let mut inner_ring = canvas(64);
+    circle(center, 8, WHITE, &mut inner_ring);
+    assert!(lit(&inner_ring).is_subset(&lit(&img)));
+
+    let mut gauge = canvas(64);
+    annular_sector(center, 8, 16, PI, 2.0 * PI, WHITE, &mut gauge);
+    // The upper half, from the left ray up to but not including the right one
+    assert!(lit(&gauge).iter().all(|&(x, y)| y < 32 || (y == 32 && x < 32)));
+    assert!(gauge.get_pixel(24, 32)[0] == 255 && gauge.get_pixel(40, 32)[0] == 0);
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use render_with_rust::{
+    line::LineMethodEnum::*,
+    model::{triangle, TriangleMethodEnum::*},
+    ssaa::{Supersampler, ResolveFilterEnum::*}
+};
+use common::{point, canvas, Lcg};
+
+#[test]
+fn filters_preserve_flat_color() {
+    let mut ssaa = Supersampler::new(12, 9, 4);
+    ssaa.clear([30, 60, 90, 255]);
+    for filter in [BOX, TENT, MITCHELL, LANCZOS] {
+        assert!(ssaa.resolve(filter).pixels().all(|p| p.0 == [30, 60, 90, 255]), "{:?}", filter);
+    }
+}
+
+#[test]
+fn axis_aligned_line_keeps_its_width() {
+    let mut ssaa = Supersampler::new(16, 8, 4);
+    ssaa.clear([0, 0, 0, 255]);
+    ssaa.line(point(2, 4), point(14, 4), [255, 255, 255, 255], BRESENHAM);
+    let image = ssaa.resolve(BOX);
+    assert_eq!(image.get_pixel(8, 4)[0], 255);
+    assert_eq!(image.get_pixel(8, 3)[0], 0);
+    assert_eq!(image.get_pixel(8, 5)[0], 0);
+}
+
+#[test]
+fn triangles_cover_the_same_area_as_at_target_resolution() {
+    let white = [255, 255, 255, 255];
+    let mut ssaa = Supersampler::new(16, 16, 4);
+    ssaa.clear([0, 0, 0, 255]);
+    ssaa.triangle(point(0, 0), point(8, 0), point(0, 8), white, EDGEFUNCTION);
+    let image = ssaa.resolve(BOX);
+    for (x, y, p) in image.enumerate_pixels() {
+        match x + y {
+            0..=6 => assert_eq!(p[0], 255, "({}, {})", x, y),
+            7     => assert!(p[0] > 0 && p[0] < 255, "({}, {})", x, y),
+            _     => assert_eq!(p[0], 0, "({}, {})", x, y),
+        }
+    }
+
+    // Coverage has the area of the pixels `triangle` fills and is centered
+    // on the triangle, not half a pixel off
+    let mut rng = Lcg::new(3);
+    for _ in 0..20 {
+        let [a, b, c] = [(); 3].map(|_| point(rng.next(40), rng.next(40)));
+        let mut ssaa = Supersampler::new(40, 40, 4);
+        ssaa.clear([0, 0, 0, 255]);
+        ssaa.triangle(a, b, c, white, EDGEFUNCTION);
+        let resolved = ssaa.resolve(BOX);
+        let mut target = canvas(40);
+        triangle(a, b, c, white, &mut target, EDGEFUNCTION);
+
+        let moments = |image: &image::RgbaImage| image.enumerate_pixels().fold((0.0, 0.0, 0.0), |(m, mx, my), (x, y, p)| {
+            let w = p[0] as f64 / 255.0;
+            (m + w, mx + w * (x as f64 + 0.5), my + w * (y as f64 + 0.5))
+        });
+        let (m, mx, my) = moments(&resolved);
+        let (area, _, _) = moments(&target);
+        if area < 20.0 {continue;}
+        assert!((m - area).abs() < 0.1 * area, "{:?} area {} vs {}", (a, b, c), m, area);
+        let center = [a, b, c].iter().fold((0.0, 0.0), |(x, y), p| (x + p.x.value as f64 / 3.0, y + p.y.value as f64 / 3.0));
+        assert!((mx / m - center.0).abs() < 0.1 && (my / m - center.1).abs() < 0.1,
+                "{:?} center ({}, {})", (a, b, c), mx / m, my / m);
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use render_with_rust::{
+    stroke::*,
+    model::triangle_aa
+};
+use common::{pt, canvas};
+
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+#[test]
+fn butt_stroke_covers_its_rectangle() {
+    let mut img = canvas(48);
+    stroke_line(pt(10.0, 10.0), pt(20.0, 10.0), WHITE, &Stroke::new(2.0, LineCapEnum::BUTT, LineJoinEnum::MITER), &mut img);
+
+    for (x, y, p) in img.enumerate_pixels() {
+        let inside = (10..20).contains(&x) && (9..11).contains(&y);
+        assert_eq!(p[0], if inside {255} else {0}, "pixel ({}, {})", x, y);
+    }
+}
+
+#[test]
+fn caps_extend_past_the_endpoints() {
+    let mut square = canvas(48);
+    let mut round  = canvas(48);
+    stroke_line(pt(10.0, 10.0), pt(20.0, 10.0), WHITE, &Stroke::new(2.0, LineCapEnum::SQUARE, LineJoinEnum::MITER), &mut square);
+    stroke_line(pt(10.0, 10.0), pt(20.0, 10.0), WHITE, &Stroke::new(2.0, LineCapEnum::ROUND, LineJoinEnum::MITER), &mut round);
+
+    assert_eq!(square.get_pixel(9, 9)[0], 255);
+    assert_eq!(square.get_pixel(20, 10)[0], 255);
+    assert_eq!(square.get_pixel(8, 9)[0], 0);
+    // The round cap only partly covers the corner pixels
+    let corner = round.get_pixel(9, 9)[0];
+    assert!(corner > 0 && corner < 255);
+}
+
+#[test]
+fn fractional_width_gives_partial_coverage() {
+    let mut img = canvas(48);
+    stroke_line(pt(4.0, 10.5), pt(40.0, 10.5), WHITE, &Stroke{width: 0.5, ..Stroke::default()}, &mut img);
+
+    assert_eq!(img.get_pixel(20, 10)[0], 128);
+    assert_eq!(img.get_pixel(20, 9)[0], 0);
+    assert_eq!(img.get_pixel(20, 11)[0], 0);
+}
+
+#[test]
+fn joins_fill_the_outer_corner() {
+    let corner = [pt(10.0, 10.0), pt(30.0, 10.0), pt(30.0, 30.0)];
+    let draw = |join| {
+        let mut img = canvas(48);
+        stroke_polyline(&corner, false, WHITE, &Stroke::new(6.0, LineCapEnum::BUTT, join), &mut img);
+        img
+    };
+    let miter = draw(LineJoinEnum::MITER);
+    let bevel = draw(LineJoinEnum::BEVEL);
+    let round = draw(LineJoinEnum::ROUND);
+
+    // The miter reaches the sharp corner at (33, 7), the bevel cuts it off
+    assert_eq!(miter.get_pixel(32, 7)[0], 255);
+    assert_eq!(bevel.get_pixel(32, 7)[0], 0);
+    assert_eq!(round.get_pixel(32, 7)[0], 0);
+    assert!(round.get_pixel(32, 8)[0] > bevel.get_pixel(32, 8)[0]);
+    // Inside the corner all three agree
+    assert_eq!(miter.get_pixel(28, 12), bevel.get_pixel(28, 12));
+    assert_eq!(miter.get_pixel(28, 12), round.get_pixel(28, 12));
+}
+
+#[test]
+fn miter_limit_falls_back_to_bevel() {
+    // A 30 degree turn back has a miter almost four widths long
+    let spike = [pt(4.0, 20.0), pt(40.0, 20.0), pt(4.0, 30.0)];
+    let mut limited   = canvas(48);
+    let mut unlimited = canvas(48);
+    stroke_polyline(&spike, false, WHITE, &Stroke{width: 4.0, miter_limit: 2.0, ..Stroke::default()}, &mut limited);
+    stroke_polyline(&spike, false, WHITE, &Stroke{width: 4.0, miter_limit: 10.0, ..Stroke::default()}, &mut unlimited);
+
+    assert_eq!(limited.get_pixel(44, 18)[0], 0);
+    assert_eq!(unlimited.get_pixel(44, 18)[0], 255);
+}
+
+#[test]
+fn overlapping_joins_blend_once() {
+    let mut img = canvas(48);
+    let zigzag = [pt(4.0, 4.0), pt(40.0, 10.0), pt(6.0, 20.0), pt(40.0, 40.0)];
+    let stroke = Stroke::new(5.0, LineCapEnum::ROUND, LineJoinEnum::ROUND);
+    stroke_polyline(&zigzag, false, [255, 255, 255, 128], &stroke, &mut img);
+
+    // Translucent white over black never exceeds a single blend
+    let full = img.get_pixel(20, 7)[0];
+    assert_eq!(full, 128);
+    assert!(img.pixels().all(|p| p[0] <= full));
+}
+
+#[test]
+fn closed_polyline_has_no_caps() {
+    let square = [pt(10.0, 10.0), pt(30.0, 10.0), pt(30.0, 30.0), pt(10.0, 30.0)];
+    let mut img = canvas(48);
+    stroke_polyline(&square, true, WHITE, &Stroke::new(2.0, LineCapEnum::BUTT, LineJoinEnum::MITER), &mut img);
+
+    // Every corner is mitered, including the one where the path closes
+    for &(x, y) in &[(9, 9), (30, 9), (30, 30), (9, 30)] {
+        assert_eq!(img.get_pixel(x, y)[0], 255, "corner ({}, {})", x, y);
+    }
+    assert_eq!(img.get_pixel(20, 20)[0], 0);
+}
+
+#[test]
+fn aa_triangle_edges_are_partial() {
+    let mut img = canvas(48);
+    triangle_aa(pt(4.0, 4.0), pt(40.0, 4.0), pt(4.0, 40.0), WHITE, &mut img);
+
+    assert_eq!(img.get_pixel(10, 10)[0], 255);
+    assert_eq!(img.get_pixel(30, 30)[0], 0);
+    // The pixel straddling the hypotenuse
+    let edge = img.get_pixel(21, 22)[0];
+    assert!(edge > 0 && edge < 255, "{}", edge);
+}
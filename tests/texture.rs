@@ -0,0 +1,73 @@
+use image::{RgbaImage, Rgba};
+use render_with_rust::{
+    backend::geometry::*,
+    texture::*
+};
+
+fn uv(u: f32, v: f32) -> Vec2f {
+    Vec2f::new(Scalar::new(u), Scalar::new(v))
+}
+
+fn checker(size: u32) -> RgbaImage {
+    RgbaImage::from_fn(size, size, |x, y| if (x + y) % 2 == 0 {Rgba([255; 4])} else {Rgba([0, 0, 0, 255])})
+}
+
+#[test]
+fn wrap_modes() {
+    let ramp = Texture::new(RgbaImage::from_fn(4, 1, |x, _| Rgba([x as u8 * 10, 0, 0, 255]))).unwrap();
+    let at = |wrap, u| Sampler::new(FilterEnum::NEAREST, wrap).sample(&ramp, uv(u, 0.5))[0];
+
+    assert_eq!(at(WrapEnum::REPEAT, 1.125), 0);
+    assert_eq!(at(WrapEnum::REPEAT, -0.125), 30);
+    assert_eq!(at(WrapEnum::CLAMP, 1.125), 30);
+    assert_eq!(at(WrapEnum::CLAMP, -3.0), 0);
+    assert_eq!(at(WrapEnum::MIRROR, 1.125), 30);
+    assert_eq!(at(WrapEnum::MIRROR, 1.875), 0);
+}
+
+#[test]
+fn bilinear_blends_neighbours() {
+    let texture = Texture::new(RgbaImage::from_fn(2, 1, |x, _| Rgba([x as u8 * 200, 0, 0, 255]))).unwrap();
+    let sampler = Sampler::new(FilterEnum::BILINEAR, WrapEnum::CLAMP);
+    assert_eq!(sampler.sample(&texture, uv(0.5, 0.5))[0], 100);
+    assert_eq!(sampler.sample(&texture, uv(0.25, 0.5))[0], 0);
+}
+
+#[test]
+fn mipmaps_filter_minified_lookups() {
+    let texture = Texture::with_mipmaps(checker(64)).unwrap();
+    assert_eq!(texture.levels(), 7);
+    assert_eq!(texture.level(6).dimensions(), (1, 1));
+
+    let sampler = Sampler::default();
+    let near = uv(1.0 / 64.0, 0.0);
+    let tiny = uv(0.0, 0.0);
+    // One texel per pixel keeps the checker, 16 texels per pixel averages it to gray
+    let sharp = sampler.sample_grad(&texture, uv(0.5 / 64.0, 0.5 / 64.0), near, tiny);
+    assert_eq!(sharp[0], 255);
+    let coarse = sampler.sample_grad(&texture, uv(0.5, 0.5), uv(0.25, 0.0), uv(0.0, 0.25));
+    assert!((coarse[0] as i32 - 128).abs() <= 2);
+
+    // Anisotropic footprint: long in u, one texel in v
+    let aniso = Sampler{anisotropy: 16, ..Sampler::default()};
+    let stretched = aniso.sample_grad(&texture, uv(0.5, 0.5), uv(0.25, 0.0), uv(0.0, 1.0 / 64.0));
+    assert!((stretched[0] as i32 - 128).abs() <= 8);
+}
+
+#[test]
+fn derivatives_from_triangle() {
+    let (dx, dy) = uv_derivatives(uv(0.0, 0.0), uv(10.0, 0.0), uv(0.0, 20.0),
+                                  uv(0.0, 0.0), uv(1.0, 0.0), uv(0.0, 1.0));
+    assert!((dx.x.value - 0.1).abs() < 1e-6 && dx.y.value.abs() < 1e-6);
+    assert!((dy.y.value - 0.05).abs() < 1e-6 && dy.x.value.abs() < 1e-6);
+}
+
+#[test]
+fn empty_images_are_rejected() {
+    assert_eq!(Texture::new(RgbaImage::new(0, 4)).unwrap_err(), TextureError::Empty{width: 0, height: 4});
+    assert!(Texture::with_mipmaps(RgbaImage::new(3, 0)).is_err());
+
+    // Non-square images still mip down to 1x1 without an empty level
+    let texture = Texture::with_mipmaps(RgbaImage::new(8, 1)).unwrap();
+    assert_eq!(texture.level(texture.levels() - 1).dimensions(), (1, 1));
+}
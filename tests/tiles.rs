@@ -0,0 +1,76 @@
+mod common;
+
+use image::RgbaImage;
+use render_with_rust::{
+    line::{line, LineMethodEnum::*},
+    model::{triangle, TriangleMethodEnum::*},
+    backend::images::set_all,
+    tiles::TileRenderer
+};
+use common::{point, Lcg};
+
+#[test]
+fn tiled_lines_match_single_threaded() {
+    let (width, height) = (300, 200);
+    let mut expected = RgbaImage::new(width, height);
+    let mut actual   = RgbaImage::new(width, height);
+    set_all(&mut expected, [0, 0, 0, 255]);
+    set_all(&mut actual,   [0, 0, 0, 255]);
+
+    let mut tiles = TileRenderer::new(32).with_threads(4);
+    let mut rng = Lcg::new(7);
+    for i in 0..500 {
+        let p0 = point(rng.next(width), rng.next(height));
+        let p1 = point(rng.next(width), rng.next(height));
+        let color  = [(i * 7) as u8, (i * 13) as u8, (i * 29) as u8, 255];
+        let method = [NAIVE1, NAIVE2, BRESENHAM, WU][i % 4];
+        line(p0, p1, color, &mut expected, method);
+        tiles.line(p0, p1, color, method);
+    }
+    tiles.render(&mut actual);
+
+    assert!(expected == actual);
+}
+
+#[test]
+fn tiled_triangles_match_single_threaded() {
+    let (width, height) = (200, 150);
+    let mut expected = RgbaImage::new(width, height);
+    let mut actual   = RgbaImage::new(width, height);
+    set_all(&mut expected, [0, 0, 0, 255]);
+    set_all(&mut actual,   [0, 0, 0, 255]);
+
+    let mut tiles = TileRenderer::new(32).with_threads(4);
+    let mut rng = Lcg::new(11);
+    for i in 0..100 {
+        let t0 = point(rng.next(width), rng.next(height));
+        let t1 = point(rng.next(width), rng.next(height));
+        let t2 = point(rng.next(width), rng.next(height));
+        let color  = [(i * 7) as u8, (i * 13) as u8, (i * 29) as u8, 255];
+        let method = [SCANLINE, EDGEFUNCTION][i % 2];
+        triangle(t0, t1, t2, color, &mut expected, method);
+        tiles.triangle(t0, t1, t2, color, method);
+    }
+    tiles.render(&mut actual);
+
+    assert!(expected == actual);
+}
+
+#[test]
+fn tiles_walk_only_their_span_of_long_lines() {
+    // Walking these end to end in every tile would take minutes
+    let (width, height) = (256, 192);
+    let mut expected = RgbaImage::new(width, height);
+    let mut actual   = RgbaImage::new(width, height);
+    let mut tiles = TileRenderer::new(32).with_threads(4);
+    for (i, &method) in [NAIVE1, NAIVE2, BRESENHAM, WU, DDA, EFLA, DOUBLESTEP, RUNSLICE].iter().enumerate() {
+        let (p0, p1) = (point(-300_000_000, -200_000_000 + i as i32), point(300_000_000, 200_000_007));
+        let color = [(i * 30) as u8, 255, 0, 255];
+        line(p0, p1, color, &mut expected, method);
+        tiles.line(p0, p1, color, method);
+    }
+    tiles.render(&mut actual);
+
+    assert!(expected == actual);
+    assert!(expected.pixels().any(|p| p[1] > 0));
+}